@@ -1,6 +1,7 @@
 use anyhow::{Context as _, Result};
 use config::Config;
 use serenity::model::id::{ChannelId, RoleId};
+use std::collections::HashMap;
 
 /// 同じ鯖の宣伝を禁止する設定
 #[derive(Debug, Default, serde::Deserialize, PartialEq, Clone)]
@@ -13,12 +14,65 @@ pub struct BanPeriodConfig {
     pub min_per_user_start: i64,
 }
 
+/// 警告の送り方
+#[derive(Debug, Default, serde::Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertDelivery {
+    /// チャンネルで投稿に返信する
+    #[default]
+    Reply,
+    /// 投稿者にDMで送る (DMを受け付けていない場合は返信する)
+    Dm,
+}
+
 #[derive(Debug, Default, serde::Deserialize, PartialEq, Clone)]
 pub struct MessageConfig {
     /// 警告の絵文字
     pub alert_emoji: String,
     /// 無期限招待リンクの作成方法紹介ページURL
     pub no_expiration_invite_link_guide: String,
+    /// メッセージの言語 (ja, en, または messages フォルダに置いたファイル名)
+    #[serde(default = "default_locale")]
+    pub locale: String,
+    /// サーバーごとのメッセージの言語 (サーバーID → 言語)
+    #[serde(default)]
+    pub guild_locales: HashMap<String, String>,
+    /// チャンネルごとのメッセージの言語 (チャンネルID → 言語)
+    #[serde(default)]
+    pub channel_locales: HashMap<String, String>,
+    /// 日時を表示するタイムゾーン (例: Asia/Tokyo, UTC)
+    #[serde(default = "default_time_zone")]
+    pub time_zone: String,
+    /// 日時のフォーマット (未指定の場合は言語ごとの既定のフォーマット)
+    #[serde(default)]
+    pub date_format: Option<String>,
+    /// 日時をDiscordのタイムスタンプ記法で表示するか (閲覧者のタイムゾーンで表示される)
+    #[serde(default)]
+    pub discord_timestamp: bool,
+    /// Discordのタイムスタンプ記法の表示形式 (t, T, d, D, f, F, R)
+    #[serde(default = "default_timestamp_style")]
+    pub timestamp_style: String,
+    /// 警告の送り方
+    #[serde(default)]
+    pub alert_delivery: AlertDelivery,
+    /// DMで警告した場合に、チャンネルに短いお知らせを返信するか
+    #[serde(default)]
+    pub public_notice: bool,
+}
+
+/// 既定のメッセージの言語
+fn default_locale() -> String {
+    "ja".to_string()
+}
+
+/// 既定のタイムゾーン
+fn default_time_zone() -> String {
+    "Asia/Tokyo".to_string()
+}
+
+/// 既定のDiscordのタイムスタンプ記法の表示形式
+fn default_timestamp_style() -> String {
+    "f".to_string()
 }
 
 #[derive(Debug, Default, serde::Deserialize, PartialEq, Clone)]
@@ -29,10 +83,297 @@ pub struct DiscordConfig {
     pub alert_sec: u64,
     /// 必要なメッセージの長さ
     pub required_message_length: usize,
+    /// 説明文に必要な異なる単語の数 (0で無効)
+    #[serde(default)]
+    pub required_distinct_words: usize,
+    /// 宣伝する鯖に必要なメンバー数 (0で無効)
+    #[serde(default)]
+    pub required_member_count: u64,
+    /// 宣伝する鯖に必要なオンラインメンバー数 (0で無効)
+    #[serde(default)]
+    pub required_online_count: u64,
+    /// NSFWな鯖の宣伝を禁止するか (NSFWチャンネルを除く)
+    #[serde(default)]
+    pub block_nsfw: bool,
     /// 警告を無視するロールID
     pub ignore_roles: Vec<RoleId>,
 }
 
+/// 短縮URLの設定
+#[derive(Debug, Default, serde::Deserialize, PartialEq, Clone)]
+pub struct ShortenerConfig {
+    /// 短縮URLのリダイレクト先を辿って招待リンクを探すか
+    pub follow_redirects: bool,
+    /// 短縮URLのドメイン
+    pub domains: Vec<String>,
+    /// 辿るリダイレクトの最大回数
+    pub max_redirects: usize,
+}
+
+/// 投稿の長さと書式の上限の設定 (すべて0で無効)
+#[derive(Debug, Default, serde::Deserialize, PartialEq, Clone)]
+pub struct LimitsConfig {
+    /// 投稿の最大文字数
+    #[serde(default)]
+    pub max_length: usize,
+    /// 投稿の最大行数
+    #[serde(default)]
+    pub max_lines: usize,
+    /// 投稿の見出しの最大数
+    #[serde(default)]
+    pub max_headers: usize,
+    /// 1つの投稿に含められる招待リンクの最大数
+    #[serde(default)]
+    pub max_invites: usize,
+}
+
+/// 禁止ワードのルール
+#[derive(Debug, Default, serde::Deserialize, PartialEq, Clone)]
+pub struct ContentFilterRule {
+    /// 禁止ワード (大文字小文字、全角半角、空白や記号を無視して部分一致)
+    #[serde(default)]
+    pub words: Vec<String>,
+    /// 禁止する正規表現 (大文字小文字を無視)
+    #[serde(default)]
+    pub patterns: Vec<String>,
+}
+
+/// 禁止ワードの設定
+#[derive(Debug, Default, serde::Deserialize, PartialEq, Clone)]
+pub struct ContentFilterConfig {
+    /// すべてのチャンネルで禁止するワード
+    #[serde(default)]
+    pub words: Vec<String>,
+    /// すべてのチャンネルで禁止する正規表現
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    /// チャンネルごとに追加で禁止するルール (チャンネルID → ルール)
+    #[serde(default)]
+    pub channels: HashMap<String, ContentFilterRule>,
+}
+
+/// 禁止されたメンションを含む投稿の扱い
+#[derive(Debug, Default, serde::Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum MentionAction {
+    /// 警告して削除する
+    #[default]
+    Reject,
+    /// メンションを取り除いて再投稿する
+    Sanitize,
+}
+
+/// メンションの設定
+#[derive(Debug, Default, serde::Deserialize, PartialEq, Clone)]
+pub struct MentionConfig {
+    /// @everyone, @here を禁止するか
+    #[serde(default)]
+    pub block_everyone: bool,
+    /// ロールメンションを禁止するか
+    #[serde(default)]
+    pub block_roles: bool,
+    /// ユーザーメンションの最大数 (未指定で無制限)
+    #[serde(default)]
+    pub max_user_mentions: Option<usize>,
+    /// 禁止されたメンションを含む投稿の扱い
+    #[serde(default)]
+    pub action: MentionAction,
+}
+
+/// Discord以外のリンクの設定
+#[derive(Debug, Default, serde::Deserialize, PartialEq, Clone)]
+pub struct LinkPolicyConfig {
+    /// 許可するドメイン (指定した場合、それ以外のドメインは禁止)
+    #[serde(default)]
+    pub allow_domains: Vec<String>,
+    /// 禁止するドメイン
+    #[serde(default)]
+    pub deny_domains: Vec<String>,
+    /// Discord以外のリンクの最大数 (未指定で無制限)
+    #[serde(default)]
+    pub max_external_links: Option<usize>,
+}
+
+/// ユーザーごとの宣伝回数の上限の設定
+#[derive(Debug, serde::Deserialize, PartialEq, Clone)]
+pub struct QuotaConfig {
+    /// 期間内に宣伝できる回数 (0で無効)
+    #[serde(default)]
+    pub posts: u64,
+    /// 宣伝回数を数える時間
+    #[serde(default = "default_quota_window_hours")]
+    pub window_hours: i64,
+    /// チャンネルごとに数えるか (falseの場合はすべてのチャンネルの合計)
+    #[serde(default)]
+    pub per_channel: bool,
+}
+
+impl Default for QuotaConfig {
+    fn default() -> Self {
+        QuotaConfig {
+            posts: 0,
+            window_hours: default_quota_window_hours(),
+            per_channel: false,
+        }
+    }
+}
+
+/// 既定の宣伝回数を数える時間
+fn default_quota_window_hours() -> i64 {
+    24
+}
+
+/// 説明文の重複検出の設定
+#[derive(Debug, serde::Deserialize, PartialEq, Clone)]
+pub struct DuplicateConfig {
+    /// 重複とみなす類似度 (0.0～1.0、0で無効)
+    #[serde(default)]
+    pub threshold: f64,
+    /// 過去の投稿と比較する日数
+    #[serde(default = "default_duplicate_days")]
+    pub days: i64,
+    /// 比較する説明文の最小の長さ (正規化後の文字数)
+    #[serde(default = "default_duplicate_min_length")]
+    pub min_length: usize,
+}
+
+impl Default for DuplicateConfig {
+    fn default() -> Self {
+        DuplicateConfig {
+            threshold: 0.0,
+            days: default_duplicate_days(),
+            min_length: default_duplicate_min_length(),
+        }
+    }
+}
+
+/// 既定の過去の投稿と比較する日数
+fn default_duplicate_days() -> i64 {
+    7
+}
+
+/// 既定の比較する説明文の最小の長さ
+fn default_duplicate_min_length() -> usize {
+    20
+}
+
+/// アカウントの作成日数とサーバーの参加時間の条件
+#[derive(Debug, Default, serde::Deserialize, PartialEq, Clone)]
+pub struct AccountAgeRule {
+    /// アカウント作成から必要な日数 (0で無効)
+    #[serde(default)]
+    pub min_account_days: i64,
+    /// サーバー参加から必要な時間 (0で無効)
+    #[serde(default)]
+    pub min_member_hours: i64,
+}
+
+/// 宣伝できるアカウントの条件の設定
+#[derive(Debug, Default, serde::Deserialize, PartialEq, Clone)]
+pub struct AccountAgeConfig {
+    /// アカウント作成から必要な日数 (0で無効)
+    #[serde(default)]
+    pub min_account_days: i64,
+    /// サーバー参加から必要な時間 (0で無効)
+    #[serde(default)]
+    pub min_member_hours: i64,
+    /// チャンネルごとの条件 (チャンネルID → 条件)
+    #[serde(default)]
+    pub channels: HashMap<String, AccountAgeRule>,
+}
+
+impl AccountAgeConfig {
+    /// チャンネルに適用する条件を取得する
+    pub fn rule(&self, channel_id: &ChannelId) -> AccountAgeRule {
+        match self.channels.get(&channel_id.to_string()) {
+            Some(rule) => rule.clone(),
+            None => AccountAgeRule {
+                min_account_days: self.min_account_days,
+                min_member_hours: self.min_member_hours,
+            },
+        }
+    }
+}
+
+/// 宣伝するサーバーの所属確認の設定
+#[derive(Debug, Default, serde::Deserialize, PartialEq, Clone)]
+pub struct OwnershipConfig {
+    /// 所属が確認できたサーバーのみ宣伝できるようにするか
+    pub required: bool,
+}
+
+/// 違反時の処分の種類
+#[derive(Debug, serde::Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum StrikeAction {
+    /// タイムアウトする
+    Timeout,
+    /// チャンネルへの投稿権限を剥奪する
+    RemoveAccess,
+}
+
+/// 違反回数ごとの処分
+#[derive(Debug, serde::Deserialize, PartialEq, Clone)]
+pub struct StrikeStep {
+    /// 処分を行う違反回数
+    pub count: u64,
+    /// 処分の種類
+    pub action: StrikeAction,
+    /// 処分の分数 (タイムアウトは最大28日、投稿権限の剥奪は0で無期限)
+    pub minutes: i64,
+}
+
+/// 繰り返し違反したユーザーへの処分の設定
+#[derive(Debug, serde::Deserialize, PartialEq, Clone)]
+pub struct StrikeConfig {
+    /// 違反回数を数える時間 (0で無効)
+    #[serde(default)]
+    pub window_hours: i64,
+    /// 処分をモデレーターに報告するチャンネルID
+    #[serde(default)]
+    pub report_channel: Option<ChannelId>,
+    /// 違反回数ごとの処分
+    #[serde(default = "default_strike_steps")]
+    pub steps: Vec<StrikeStep>,
+}
+
+impl Default for StrikeConfig {
+    fn default() -> Self {
+        StrikeConfig {
+            window_hours: 0,
+            report_channel: None,
+            steps: default_strike_steps(),
+        }
+    }
+}
+
+/// 既定の違反回数ごとの処分 (3回でタイムアウト、5回で投稿権限の剥奪)
+fn default_strike_steps() -> Vec<StrikeStep> {
+    vec![
+        StrikeStep {
+            count: 3,
+            action: StrikeAction::Timeout,
+            minutes: 60,
+        },
+        StrikeStep {
+            count: 5,
+            action: StrikeAction::RemoveAccess,
+            minutes: 1440,
+        },
+    ]
+}
+
+/// 宣伝の承認制の設定
+#[derive(Debug, Default, serde::Deserialize, PartialEq, Clone)]
+pub struct ApprovalConfig {
+    /// 承認制にするチャンネルID
+    #[serde(default)]
+    pub channels: Vec<ChannelId>,
+    /// 承認待ちの宣伝を送るスタッフ用のチャンネルID (未設定で無効)
+    #[serde(default)]
+    pub review_channel: Option<ChannelId>,
+}
+
 /// アプリケーションの設定
 #[derive(Debug, Default, serde::Deserialize, PartialEq, Clone)]
 pub struct AppConfig {
@@ -42,6 +383,39 @@ pub struct AppConfig {
     pub ban_period: BanPeriodConfig,
     /// メッセージ
     pub message: MessageConfig,
+    /// 短縮URLの設定
+    #[serde(default)]
+    pub shortener: ShortenerConfig,
+    /// 投稿の長さと書式の上限の設定
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    /// 禁止ワードの設定
+    #[serde(default)]
+    pub content_filter: ContentFilterConfig,
+    /// メンションの設定
+    #[serde(default)]
+    pub mentions: MentionConfig,
+    /// Discord以外のリンクの設定
+    #[serde(default)]
+    pub link_policy: LinkPolicyConfig,
+    /// ユーザーごとの宣伝回数の上限の設定
+    #[serde(default)]
+    pub quota: QuotaConfig,
+    /// 宣伝できるアカウントの条件の設定
+    #[serde(default)]
+    pub account_age: AccountAgeConfig,
+    /// 説明文の重複検出の設定
+    #[serde(default)]
+    pub duplicate: DuplicateConfig,
+    /// 宣伝するサーバーの所属確認の設定
+    #[serde(default)]
+    pub ownership: OwnershipConfig,
+    /// 繰り返し違反したユーザーへの処分の設定
+    #[serde(default)]
+    pub strike: StrikeConfig,
+    /// 宣伝の承認制の設定
+    #[serde(default)]
+    pub approval: ApprovalConfig,
 }
 
 impl AppConfig {
@@ -0,0 +1,196 @@
+use anyhow::{Context as _, Result};
+use regex::{Regex, RegexBuilder};
+use serenity::model::id::ChannelId;
+use std::collections::HashMap;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::app_config::{ContentFilterConfig, ContentFilterRule};
+
+/// 単語ごとに正規化する (URLを除き、単語の中の記号を詰めて英数字と文字だけを小文字で残す)
+fn normalize_words(text: &str) -> String {
+    text.split_whitespace()
+        .filter(|word| !word.contains("://"))
+        .map(|word| {
+            word.nfkc()
+                .flat_map(char::to_lowercase)
+                .filter(|c| c.is_alphanumeric())
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 単語の区切りが必要な文字か (空白で区切らない日本語などは途中からでも一致させる)
+fn is_word_char(c: char) -> bool {
+    let cjk = matches!(c,
+        '\u{3040}'..='\u{30ff}' // ひらがな、カタカナ
+        | '\u{3400}'..='\u{4dbf}' // CJK統合漢字拡張A
+        | '\u{4e00}'..='\u{9fff}' // CJK統合漢字
+        | '\u{ac00}'..='\u{d7af}' // ハングル
+    );
+    c.is_alphanumeric() && !cjk
+}
+
+/// 正規化したテキストに、単語の途中で切れずに禁止ワードが含まれるか
+fn contains_words(text: &str, word: &str) -> bool {
+    let (first, last) = match (word.chars().next(), word.chars().next_back()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return false,
+    };
+    text.match_indices(word).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + word.len()..].chars().next();
+        !before.is_some_and(|c| is_word_char(c) && is_word_char(first))
+            && !after.is_some_and(|c| is_word_char(c) && is_word_char(last))
+    })
+}
+
+/// コンパイル済みの禁止ワードのルール
+#[derive(Debug, Default)]
+struct CompiledRule {
+    /// 禁止ワードと、単語ごとに正規化したもの、単語の区切りを詰めたもの
+    words: Vec<(String, String, String)>,
+    /// 禁止する正規表現
+    patterns: Vec<Regex>,
+}
+
+impl CompiledRule {
+    /// 設定からルールを作成する
+    fn new(rule: &ContentFilterRule) -> Result<CompiledRule> {
+        let words = rule
+            .words
+            .iter()
+            .map(|word| {
+                let normalized = normalize_words(word);
+                let compact = normalized.replace(' ', "");
+                (word.clone(), normalized, compact)
+            })
+            .filter(|(_word, normalized, _compact)| !normalized.is_empty())
+            .collect();
+        let patterns = rule
+            .patterns
+            .iter()
+            .map(|pattern| {
+                RegexBuilder::new(pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .with_context(|| format!("禁止ワードの正規表現のパースに失敗: {}", pattern))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(CompiledRule { words, patterns })
+    }
+
+    /// テキストに含まれる禁止ワードを探す
+    fn find(&self, text: &str, normalized: &str) -> Option<String> {
+        // 禁止ワードは単語の中の記号を無視し、単語単位で探す ("free.nitro" のように詰めて書かれたものも探す)
+        if let Some((word, _normalized, _compact)) =
+            self.words.iter().find(|(_word, word_normalized, compact)| {
+                contains_words(normalized, word_normalized) || contains_words(normalized, compact)
+            })
+        {
+            return Some(word.clone());
+        }
+
+        // 正規表現は正規化したテキストに対して探す
+        self.patterns
+            .iter()
+            .find_map(|pattern| pattern.find(text))
+            .map(|found| found.as_str().to_string())
+    }
+}
+
+/// 禁止ワードフィルター
+#[derive(Debug, Default)]
+pub struct ContentFilter {
+    /// すべてのチャンネルに適用するルール
+    global: CompiledRule,
+    /// チャンネルごとに追加で適用するルール
+    channels: HashMap<ChannelId, CompiledRule>,
+}
+
+impl ContentFilter {
+    /// 設定からフィルターを作成する
+    pub fn new(config: &ContentFilterConfig) -> Result<ContentFilter> {
+        let global = CompiledRule::new(&ContentFilterRule {
+            words: config.words.clone(),
+            patterns: config.patterns.clone(),
+        })?;
+        let channels = config
+            .channels
+            .iter()
+            .map(|(channel_id, rule)| {
+                let channel_id = ChannelId(
+                    channel_id
+                        .parse()
+                        .with_context(|| format!("チャンネルIDのパースに失敗: {}", channel_id))?,
+                );
+                Ok((channel_id, CompiledRule::new(rule)?))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+        Ok(ContentFilter { global, channels })
+    }
+
+    /// テキストに含まれる禁止ワードを探す (テキストは正規化済みであること)
+    pub fn find<'a>(
+        &self,
+        channel_id: &ChannelId,
+        texts: impl IntoIterator<Item = &'a str>,
+    ) -> Option<String> {
+        let channel_rule = self.channels.get(channel_id);
+        texts.into_iter().find_map(|text| {
+            let normalized = normalize_words(text);
+            self.global
+                .find(text, &normalized)
+                .or_else(|| channel_rule?.find(text, &normalized))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_forbidden_words() {
+        let mut channels = HashMap::new();
+        channels.insert(
+            "1".to_string(),
+            ContentFilterRule {
+                words: vec!["限定".to_string()],
+                patterns: vec![],
+            },
+        );
+        let filter = ContentFilter::new(&ContentFilterConfig {
+            words: vec!["free nitro".to_string()],
+            patterns: vec![r"steam\s*gift".to_string()],
+            channels,
+        })
+        .unwrap();
+
+        // (チャンネルID, テキスト, 見つかるべき禁止ワード)
+        let cases = [
+            (1, "Get FREE Nitro now", Some("free nitro")),
+            (1, "f.r.e.e n-i-t-r-o", Some("free nitro")),
+            (1, "ｆｒｅｅ ｎｉｔｒｏ", Some("free nitro")),
+            (1, "free.nitro here", Some("free nitro")),
+            // 別の単語の一部は禁止ワードとみなさない
+            (1, "carefree nitrogen", None),
+            (1, "free nitrogen tank", None),
+            (1, "carefree nitro", None),
+            (1, "freenitrogen", None),
+            (1, "Steam Gift card", Some("Steam Gift")),
+            (1, "期間限定イベント", Some("限定")),
+            (2, "期間限定イベント", None),
+            (2, "楽しいサーバーです", None),
+        ];
+        for (channel_id, text, expected) in cases {
+            assert_eq!(
+                filter.find(&ChannelId(channel_id), [text]).as_deref(),
+                expected,
+                "{}",
+                text
+            );
+        }
+    }
+}
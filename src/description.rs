@@ -0,0 +1,249 @@
+use anyhow::{Context as _, Result};
+use regex::Regex;
+use std::collections::HashSet;
+
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// 類似度の計算に使用する文字数
+const SHINGLE_SIZE: usize = 3;
+
+/// 同じ文字が続いた場合に数える最大の回数
+const MAX_REPEAT: usize = 2;
+
+/// 説明文の評価
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct DescriptionScore {
+    /// 説明文の長さ (書記素クラスタ単位)
+    pub length: usize,
+    /// 異なる単語の数
+    pub distinct_words: usize,
+}
+
+/// 説明文から装飾や記法を取り除く
+fn strip_markup(description: &str) -> Result<String> {
+    // メンション、チャンネル、カスタム絵文字、タイムスタンプ、URLを取り除く
+    let markup_regex = Regex::new(r"(?i)<(?:@[!&]?|#|a?:\w+:|t:)[^>]*>|:\w+:|https?://\S+")
+        .context("正規表現のパターンの作成に失敗")?;
+    let description = markup_regex.replace_all(description, " ");
+    // 見出し、引用、リストなどの行頭の記法を取り除く
+    let line_regex = Regex::new(r"(?m)^\s*(?:#{1,3}|>{1,3}|-#|[-*+]|\d+\.)\s+")
+        .context("正規表現のパターンの作成に失敗")?;
+    let description = line_regex.replace_all(&description, "");
+    // 強調、打ち消し線、スポイラーなどの記号を取り除く
+    Ok(description.replace(['*', '_', '~', '`', '|', '\\'], ""))
+}
+
+/// 書記素クラスタ (見た目上の1文字) に分割する
+///
+/// 結合文字、異体字セレクタ、絵文字の肌の色、ゼロ幅接合子で繋がった絵文字、国旗を1文字として扱う簡易的な実装
+fn graphemes(text: &str) -> Vec<&str> {
+    let mut clusters: Vec<&str> = vec![];
+    let mut start = 0;
+    let mut prev: Option<char> = None;
+    let mut regional_indicators = 0;
+    for (index, c) in text.char_indices() {
+        let is_regional_indicator = matches!(c, '\u{1F1E6}'..='\u{1F1FF}');
+        let extends = match prev {
+            None => false,
+            Some(prev) => {
+                is_combining_mark(c)
+                    || matches!(
+                        c,
+                        '\u{200D}'
+                            | '\u{FE00}'..='\u{FE0F}'
+                            | '\u{1F3FB}'..='\u{1F3FF}'
+                            | '\u{E0020}'..='\u{E007F}'
+                    )
+                    || prev == '\u{200D}'
+                    || (is_regional_indicator && regional_indicators % 2 == 1)
+            }
+        };
+        if !extends && index > 0 {
+            clusters.push(&text[start..index]);
+            start = index;
+        }
+        regional_indicators = if is_regional_indicator {
+            regional_indicators + 1
+        } else {
+            0
+        };
+        prev = Some(c);
+    }
+    if start < text.len() {
+        clusters.push(&text[start..]);
+    }
+    clusters
+}
+
+/// 説明文の長さと単語数を評価する
+///
+/// 装飾や記法、絵文字、記号は数えず、連続する空白は1文字、同じ文字の繰り返しは最大2文字として数える
+pub fn score(description: &str) -> Result<DescriptionScore> {
+    let description = strip_markup(&description.nfkc().collect::<String>())?;
+
+    // 文字を数える
+    let mut length = 0;
+    let mut repeat = 0;
+    let mut prev: Option<&str> = None;
+    let mut pending_space = false;
+    for grapheme in graphemes(&description) {
+        let base = grapheme.chars().next().unwrap_or(' ');
+        if base.is_whitespace() {
+            // 連続する空白は単語の間で1文字として数える
+            pending_space = length > 0;
+            prev = None;
+            continue;
+        }
+        if !base.is_alphanumeric() {
+            // 絵文字や記号は数えない
+            continue;
+        }
+        if prev == Some(grapheme) {
+            repeat += 1;
+        } else {
+            repeat = 1;
+        }
+        prev = Some(grapheme);
+        if repeat > MAX_REPEAT {
+            continue;
+        }
+        if pending_space {
+            length += 1;
+            pending_space = false;
+        }
+        length += 1;
+    }
+
+    // 異なる単語を数える
+    let distinct_words = description
+        .split(|c: char| !c.is_alphanumeric() && !is_combining_mark(c))
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect::<HashSet<_>>()
+        .len();
+
+    Ok(DescriptionScore {
+        length,
+        distinct_words,
+    })
+}
+
+/// 重複検出用に説明文を正規化する (URLを除き、英数字と文字だけを小文字で残す)
+pub fn fingerprint(description: &str) -> String {
+    description
+        .split_whitespace()
+        .filter(|word| !word.contains("://"))
+        .collect::<String>()
+        .nfkc()
+        .flat_map(char::to_lowercase)
+        .filter(|c| c.is_alphanumeric())
+        .collect()
+}
+
+/// 正規化した文字列を一定の文字数ごとに区切った集合を作る
+fn shingles(fingerprint: &str) -> HashSet<&str> {
+    let indices = fingerprint
+        .char_indices()
+        .map(|(index, _)| index)
+        .chain(std::iter::once(fingerprint.len()))
+        .collect::<Vec<_>>();
+    indices
+        .windows(SHINGLE_SIZE + 1)
+        .map(|window| &fingerprint[window[0]..window[SHINGLE_SIZE]])
+        .collect()
+}
+
+/// 正規化した2つの説明文の類似度 (0.0～1.0) を計算する
+pub fn similarity(a: &str, b: &str) -> f64 {
+    if a == b {
+        return 1.0;
+    }
+
+    // 集合の重なり具合 (Jaccard係数) を類似度とする
+    let a = shingles(a);
+    let b = shingles(b);
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(&b).count() as f64 / union as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score_length() {
+        let cases = [
+            ("Hello world", 11),
+            // 空白はまとめて1文字
+            ("  Hello \n\n   world  ", 11),
+            // 同じ文字の繰り返しは2文字まで
+            ("わーーーーーーい！！！", 4),
+            ("wwwwwwwwww", 2),
+            // 絵文字、記号、装飾は数えない
+            ("🎉🎉🎉 **楽しい** 🎉🎉🎉", 3),
+            ("# 見出し\n> 引用", 6),
+            (
+                "<@123456789> <#123456789> <:emoji:123456789> :smile: こんにちは",
+                5,
+            ),
+            ("詳細は https://example.com へ", 5),
+            // 結合文字や国旗は1文字
+            ("e\u{301}", 1),
+            ("🇯🇵 日本", 2),
+        ];
+        for (description, expected) in cases {
+            assert_eq!(
+                score(description).unwrap().length,
+                expected,
+                "{:?}",
+                description
+            );
+        }
+    }
+
+    #[test]
+    fn test_score_distinct_words() {
+        let cases = [
+            ("Hello world", 2),
+            ("spam spam SPAM spam", 1),
+            ("fun, games & chat!", 3),
+            ("", 0),
+        ];
+        for (description, expected) in cases {
+            assert_eq!(
+                score(description).unwrap().distinct_words,
+                expected,
+                "{:?}",
+                description
+            );
+        }
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let cases = [
+            ("Hello, World!", "helloworld"),
+            ("ＡＢＣ　ｄｅｆ", "abcdef"),
+            ("楽しい  サーバーです！！ 🎉", "楽しいサーバーです"),
+            ("詳細は https://example.com を見てね", "詳細はを見てね"),
+        ];
+        for (description, expected) in cases {
+            assert_eq!(fingerprint(description), expected, "{}", description);
+        }
+    }
+
+    #[test]
+    fn test_similarity() {
+        let a = fingerprint("雑談やゲームを楽しむサーバーです。初心者歓迎！");
+        let b = fingerprint("雑談やゲームを楽しむサーバーです!! 初心者大歓迎");
+        let c = fingerprint("イラストを描く人が集まる創作サーバー");
+
+        assert_eq!(similarity(&a, &a), 1.0);
+        assert!(similarity(&a, &b) > 0.7);
+        assert!(similarity(&a, &c) < 0.2);
+        assert_eq!(similarity("", "ab"), 0.0);
+    }
+}
@@ -1,48 +1,273 @@
-use anyhow::{Context as _, Error, Result};
+use anyhow::{anyhow, Context as _, Error, Result};
 use chrono::{DateTime, Duration, NaiveDateTime, Utc};
-use chrono_tz::Tz::{self, Japan};
+use chrono_tz::Tz;
 use futures::future::{join_all, try_join_all};
 use log::{error, warn};
+use regex::Regex;
 use serenity::model::{
     event::MessageUpdateEvent,
     gateway::Ready,
     guild::Member,
     id::{ChannelId, GuildId, MessageId},
+    interactions::Interaction,
     user::User,
 };
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::time::sleep;
 
-use crate::app_config::AppConfig;
-use crate::history_log::{HistoryFindKey, HistoryLog, HistoryRecord};
-use crate::invite_finder::{DiscordInviteLink, InviteFinder};
+use crate::app_config::{AlertDelivery, AppConfig, MentionAction};
+use crate::content_filter::ContentFilter;
+use crate::description;
+use crate::history_log::{
+    DescriptionRecord, GuildListKind, HistoryFindKey, HistoryLog, HistoryRecord, RepostKey,
+};
+use crate::invite_finder::{domain_matches, DiscordInviteLink, InviteFinder};
+use crate::message_catalog::MessageCatalog;
+use button::Warning;
+use post::Post;
+use repost::Repost;
+
+mod approval;
+mod button;
+mod post;
+mod promote;
+mod reminder;
+mod repost;
+mod slash_command;
+mod strike;
 
 use serenity::async_trait;
-use serenity::model::channel::Message;
+use serenity::builder::CreateEmbed;
+use serenity::model::channel::{Message, MessageType};
 use serenity::prelude::*;
 
+/// 期限の来た処理を確認する間隔 (秒)
+const TIMER_INTERVAL_SECS: u64 = 60;
+
+/// 警告メッセージの内容
+struct Alert {
+    /// 違反の種類 (メッセージカタログのセクション名)
+    reason: &'static str,
+    /// 本文
+    content: String,
+    /// 埋め込み
+    embed: CreateEmbed,
+    /// 次に宣伝できる日時 (待てば宣伝できる警告の場合)
+    due: Option<DateTime<Utc>>,
+    /// 待てば宣伝できるサーバーのギルドIDと招待コード (クールダウンの警告の場合)
+    due_invite: Option<(GuildId, String)>,
+}
+
+/// 投稿内容の検証結果
+enum Verdict {
+    /// 違反あり
+    Alert(Alert),
+    /// 違反なし
+    Pass {
+        /// 招待リンク
+        invites: Vec<DiscordInviteLink>,
+        /// 正規化した説明文
+        fingerprint: String,
+    },
+}
+
 /// イベント受信リスナー
 pub struct Handler {
     /// 設定
     app_config: AppConfig,
     /// 履歴
     history: HistoryLog,
+    /// メッセージカタログ
+    messages: MessageCatalog,
+    /// ボタンを表示している警告 (警告メッセージIDをキーとする)
+    warnings: Mutex<HashMap<MessageId, Warning>>,
+    /// 期限の来た処理を定期的に確認するループが動いているか
+    timers_running: AtomicBool,
+    /// 日時を表示するタイムゾーン
+    time_zone: Tz,
+    /// 禁止ワードフィルター
+    content_filter: ContentFilter,
 }
 
 impl Handler {
     /// コンストラクタ
-    pub fn new(app_config: AppConfig, history: HistoryLog) -> Result<Self> {
+    pub fn new(
+        app_config: AppConfig,
+        history: HistoryLog,
+        messages: MessageCatalog,
+    ) -> Result<Self> {
+        let time_zone = app_config
+            .message
+            .time_zone
+            .parse::<Tz>()
+            .map_err(|why| anyhow!("タイムゾーンのパースに失敗: {}", why))?;
+        let content_filter = ContentFilter::new(&app_config.content_filter)
+            .context("禁止ワードの設定の読み込みに失敗")?;
+
         Ok(Self {
             app_config,
             history,
+            messages,
+            warnings: Mutex::new(HashMap::new()),
+            timers_running: AtomicBool::new(false),
+            time_zone,
+            content_filter,
         })
     }
 
-    /// 警告を一定時間後に削除する
+    /// チャンネルで使用するメッセージの言語を取得する
+    fn locale(&self, channel_id: ChannelId, guild_id: Option<GuildId>) -> &str {
+        let config = &self.app_config.message;
+        config
+            .channel_locales
+            .get(&channel_id.to_string())
+            .or_else(|| config.guild_locales.get(&guild_id?.to_string()))
+            .unwrap_or(&config.locale)
+    }
+
+    /// メッセージカタログからメッセージを取得する
+    fn text(&self, locale: &str, key: &str, args: &[(&str, String)]) -> String {
+        // どのメッセージでも使用できる値
+        let common_args = [
+            ("emoji", self.app_config.message.alert_emoji.clone()),
+            (
+                "guide",
+                self.app_config
+                    .message
+                    .no_expiration_invite_link_guide
+                    .clone(),
+            ),
+            ("alert_sec", self.app_config.discord.alert_sec.to_string()),
+        ];
+        let args = args.iter().cloned().chain(common_args).collect::<Vec<_>>();
+        self.messages.get(locale, key, &args)
+    }
+
+    /// 日時をメッセージ用にフォーマットする
+    fn format_date(&self, locale: &str, date: &DateTime<Utc>) -> String {
+        let config = &self.app_config.message;
+        // Discordのタイムスタンプ記法で表示する
+        if config.discord_timestamp {
+            return format!("<t:{}:{}>", date.timestamp(), config.timestamp_style);
+        }
+
+        let date_format = match &config.date_format {
+            Some(date_format) => date_format.clone(),
+            None => self.text(locale, "common.date_format", &[]),
+        };
+        date.with_timezone(&self.time_zone)
+            .format(&date_format)
+            .to_string()
+    }
+
+    /// 警告メッセージの本文、タイトル、説明文をメッセージカタログから作成する
+    fn new_alert(&self, locale: &str, section: &'static str, args: &[(&str, String)]) -> Alert {
+        let mut embed = CreateEmbed::default();
+        embed.title(self.text(locale, &format!("{}.title", section), args));
+        embed.description(self.text(locale, &format!("{}.description", section), args));
+        Alert {
+            reason: section,
+            content: self.text(locale, &format!("{}.content", section), args),
+            embed,
+            due: None,
+            due_invite: None,
+        }
+    }
+
+    /// 警告メッセージを送信する (設定に応じて返信またはDM、送信したメッセージを返す)
+    async fn send_alert(
+        &self,
+        ctx: &Context,
+        msg: &Message,
+        post: &Post,
+        alert: Alert,
+    ) -> Result<Vec<Message>> {
+        let locale = self.locale(post.channel_id, post.guild_id);
+        let Alert {
+            content,
+            mut embed,
+            due,
+            due_invite,
+            ..
+        } = alert;
+        // 削除される前にコピーするよう案内する
+        embed.field(
+            self.text(locale, "common.copy_title", &[]),
+            self.text(locale, "common.copy_body", &[]),
+            false,
+        );
+
+        // DMで警告する設定の場合、投稿者にDMで送る (DMを受け付けていない場合は返信する)
+        let dm = match self.app_config.message.alert_delivery {
+            AlertDelivery::Reply => None,
+            AlertDelivery::Dm => match msg
+                .author
+                .direct_message(ctx, |m| {
+                    m.content(&content);
+                    m.set_embed(embed.clone());
+                    m.set_components(self.warning_buttons(locale, due.is_some(), true, false))
+                })
+                .await
+            {
+                Ok(dm) => Some(dm),
+                Err(why) => {
+                    warn!("警告メッセージのDM送信に失敗: {:?}", why);
+                    None
+                }
+            },
+        };
+
+        let reply = match dm {
+            // DMで警告した場合は、設定に応じて短いお知らせのみ返信する
+            Some(_) if !self.app_config.message.public_notice => None,
+            Some(_) => Some(
+                msg.channel_id
+                    .send_message(ctx, |m| {
+                        m.reference_message(msg);
+                        m.content(self.text(
+                            locale,
+                            "common.dm_notice",
+                            &[("user", msg.author.mention().to_string())],
+                        ));
+                        m.set_components(self.warning_buttons(locale, false, false, true))
+                    })
+                    .await
+                    .context("警告のお知らせの送信に失敗")?,
+            ),
+            None => Some(
+                msg.channel_id
+                    .send_message(ctx, |m| {
+                        m.reference_message(msg);
+                        m.content(content);
+                        m.set_embed(embed);
+                        m.set_components(self.warning_buttons(locale, due.is_some(), true, true))
+                    })
+                    .await
+                    .context("警告メッセージの構築に失敗")?,
+            ),
+        };
+        let sent = dm.into_iter().chain(reply).collect::<Vec<_>>();
+
+        // ボタンの操作に備えて警告を記録 (期限を過ぎたDMの警告は取り除く)
+        let warning = Warning::new(msg, post, due, due_invite);
+        let mut warnings = self.warnings.lock().await;
+        let now = Utc::now();
+        warnings.retain(|_id, warning| warning.expires_at > now);
+        for message in &sent {
+            warnings.insert(message.id, warning.clone());
+        }
+
+        Ok(sent)
+    }
+
+    /// 一定時間後に警告と投稿を削除する (DMの警告は残す、フォーラムの投稿はスレッドごと削除する)
     async fn wait_and_delete_message(
         &self,
         ctx: &Context,
         msg: &Message,
-        reply: &Message,
+        post: &Post,
+        replies: &[Message],
     ) -> Result<()> {
         // 一定時間待つ
         sleep(tokio::time::Duration::from_secs(
@@ -50,11 +275,45 @@ impl Handler {
         ))
         .await;
 
-        // 警告メッセージを削除
-        reply
-            .delete(ctx)
-            .await
-            .with_context(|| format!("警告メッセージの削除に失敗: {}", reply.id))?;
+        // モデレーターに許可された場合は投稿を残す (警告メッセージは削除済み)
+        let overridden = {
+            let mut warnings = self.warnings.lock().await;
+            let overridden = replies
+                .iter()
+                .filter_map(|reply| warnings.get(&reply.id))
+                .any(|warning| warning.overridden);
+            // チャンネルの警告は削除するため取り除く (DMの警告は期限までボタンを使えるよう残す)
+            for reply in replies
+                .iter()
+                .filter(|reply| reply.channel_id == msg.channel_id)
+            {
+                warnings.remove(&reply.id);
+            }
+            overridden
+        };
+        if overridden {
+            return Ok(());
+        }
+
+        // フォーラムの投稿は最初のメッセージだけ消しても残るため、スレッドごと削除する
+        if let (Some(_title), Some(thread_id)) = (&post.title, post.thread_id) {
+            thread_id
+                .delete(ctx)
+                .await
+                .with_context(|| format!("対象スレッドの削除に失敗: {}", thread_id))?;
+            return Ok(());
+        }
+
+        // チャンネルの警告メッセージを削除
+        for reply in replies
+            .iter()
+            .filter(|reply| reply.channel_id == msg.channel_id)
+        {
+            reply
+                .delete(ctx)
+                .await
+                .with_context(|| format!("警告メッセージの削除に失敗: {}", reply.id))?;
+        }
         // 該当メッセージを削除
         msg.channel_id
             .delete_message(ctx, msg.id)
@@ -65,45 +324,35 @@ impl Handler {
     }
 
     /// 招待コードを検証する
-    async fn check_invite_links<'t>(
+    async fn check_invite_links(
         &self,
-        ctx: &Context,
-        msg: &Message,
-        invites: &[DiscordInviteLink<'t>],
-    ) -> Result<Option<Message>> {
+        post: &Post,
+        invites: &[DiscordInviteLink],
+    ) -> Result<Option<Alert>> {
+        let locale = self.locale(post.channel_id, post.guild_id);
+
         // 無効な招待コードを集める
         let invalid_invites = invites
             .iter()
-            .filter(|x| !x.guild_id.is_some())
+            .filter(|x| x.guild_id.is_none())
             .collect::<Vec<_>>();
         // 無効なリンクがある
         if !invalid_invites.is_empty() {
             // 警告メッセージを構築
-            let reply = msg
-                .channel_id
-                .send_message(ctx, |m| {
-                    m.reference_message(msg);
-                    m.content("招待リンクがリンク切れしています！\n以下の手順で招待リンクを作成して再度投稿してね");
-                    m.embed(|e| {
-                        e.title("無効な招待リンク");
-                        e.description(format!("有効な招待リンクのみ使用できます\n招待リンクの作り方は[こちらをクリック！]({})", self.app_config.message.no_expiration_invite_link_guide));
-                        e.fields(
-                            invalid_invites
-                                .iter()
-                                .map(|x| ("招待コード", format!("`{}`", x.invite_code), false)),
-                        );
-                        e.field(
-                            format!("投稿を{}秒以内にコピーしてください！", self.app_config.discord.alert_sec),
-                            format!("あなたの投稿は{}秒後に削除されます。メッセージの編集機能は使用せずメモ帳などにコピーして修正後、再投稿してください", self.app_config.discord.alert_sec),
-                            false
-                        );
-                        e
-                    })
-                })
-                .await
-                .context("警告メッセージの構築に失敗")?;
+            let mut alert = self.new_alert(locale, "invalid_invite", &[]);
+            alert.embed.fields(invalid_invites.iter().map(|x| {
+                let args = [
+                    ("code", x.invite_code.clone()),
+                    ("source", self.text(locale, x.source.message_key(), &[])),
+                ];
+                (
+                    self.text(locale, "invalid_invite.field_name", &args),
+                    self.text(locale, "invalid_invite.field_value", &args),
+                    false,
+                )
+            }));
 
-            return Ok(Some(reply));
+            return Ok(Some(alert));
         }
 
         // 期限付きの招待コードを集める
@@ -114,56 +363,259 @@ impl Handler {
         // 期限付きのリンクがある
         if !expirable_invites.is_empty() {
             // 警告メッセージを構築
-            let reply = msg
-                .channel_id
-                .send_message(ctx, |m| {
-                    m.reference_message(msg);
-                    m.content("無期限招待リンクを作成しましょう！\n以下の手順で無期限招待リンクを作って再度投稿してね");
-                    m.embed(|e| {
-                        e.title(format!(
-                            "{0}期限付き招待リンクは使用できません{0}",
-                            self.app_config.message.alert_emoji
-                        ));
-                        e.description(format!("招待リンクは無期限のものだけ使用できます\n無期限招待リンクの作り方は[こちらをクリック！]({})", self.app_config.message.no_expiration_invite_link_guide));
-                        e.fields(
-                            expirable_invites
-                                .iter()
-                                .filter_map(|x| {
-                                    Some((
-                                        x,
-                                        x.expires_at?
-                                            .with_timezone(&Japan)
-                                            .format("%Y年%m月%d日 %H時%M分%S秒"),
-                                    ))
-                                })
-                                .map(|(x, expires_at)| {
-                                    (format!("`{}` の有効期限", x.invite_code), expires_at, false)
-                                }),
-                        );
-                        e.field(
-                            format!("投稿を{}秒以内にコピーしてください！", self.app_config.discord.alert_sec),
-                            format!("あなたの投稿は{}秒後に削除されます。メッセージの編集機能は使用せずメモ帳などにコピーして修正後、再投稿してください", self.app_config.discord.alert_sec),
-                            false
-                        );
-                        e
-                    })
-                })
-                .await
-                .context("警告メッセージの構築に失敗")?;
+            let mut alert = self.new_alert(locale, "expirable_invite", &[]);
+            alert.embed.fields(
+                expirable_invites
+                    .iter()
+                    .filter_map(|x| Some((x, x.expires_at?.with_timezone(&Utc))))
+                    .map(|(x, expires_at)| {
+                        let args = [
+                            ("code", x.invite_code.clone()),
+                            ("expires_at", self.format_date(locale, &expires_at)),
+                        ];
+                        (
+                            self.text(locale, "expirable_invite.field_name", &args),
+                            self.text(locale, "expirable_invite.field_value", &args),
+                            false,
+                        )
+                    }),
+            );
 
-            return Ok(Some(reply));
+            return Ok(Some(alert));
         }
 
         Ok(None)
     }
 
+    /// 禁止リストに登録された鯖を宣伝していないか検証する
+    async fn check_invite_denylist(
+        &self,
+        post: &Post,
+        invites: &[DiscordInviteLink],
+    ) -> Result<Option<Alert>> {
+        // 禁止リストに登録された招待コードを集める
+        let denied_invites = try_join_all(invites.iter().map(|invite| async move {
+            let record = match invite.guild_id {
+                Some(invite_guild_id) => {
+                    self.history
+                        .get_guild_list(&post.guild_id, &invite_guild_id)
+                        .await?
+                }
+                None => None,
+            };
+            Ok::<_, Error>(
+                record
+                    .filter(|record| record.kind == GuildListKind::Deny)
+                    .map(|record| (invite, record)),
+            )
+        }))
+        .await?
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+        if denied_invites.is_empty() {
+            return Ok(None);
+        }
+
+        // 警告メッセージを構築
+        let locale = self.locale(post.channel_id, post.guild_id);
+        let mut alert = self.new_alert(locale, "denied_server", &[]);
+        alert.embed.fields(denied_invites.iter().map(|(x, record)| {
+            let reason = match &record.reason {
+                Some(reason) => reason.clone(),
+                None => self.text(locale, "common.no_reason", &[]),
+            };
+            let args = [("code", x.invite_code.clone()), ("reason", reason)];
+            (
+                self.text(locale, "denied_server.field_name", &args),
+                self.text(locale, "denied_server.field_value", &args),
+                false,
+            )
+        }));
+
+        Ok(Some(alert))
+    }
+
+    /// 宣伝するサーバーに所属していることが確認済みか検証する
+    async fn check_invite_ownership(
+        &self,
+        post: &Post,
+        invites: &[DiscordInviteLink],
+    ) -> Result<Option<Alert>> {
+        // 設定で無効になっている
+        if !self.app_config.ownership.required {
+            return Ok(None);
+        }
+
+        // 所属が確認できていない招待コードを集める
+        let unverified_invites = try_join_all(invites.iter().map(|invite| async move {
+            let verified = match invite.guild_id {
+                Some(invite_guild_id) => self
+                    .history
+                    .get_ownership(&post.guild_id, &invite_guild_id, &post.author.id)
+                    .await?
+                    .is_some_and(|record| record.verified),
+                None => false,
+            };
+            Ok::<_, Error>((!verified).then_some(invite))
+        }))
+        .await?
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+        if unverified_invites.is_empty() {
+            return Ok(None);
+        }
+
+        // 警告メッセージを構築
+        let locale = self.locale(post.channel_id, post.guild_id);
+        let mut alert = self.new_alert(locale, "ownership", &[]);
+        alert.embed.fields(unverified_invites.iter().map(|x| {
+            let args = [
+                ("code", x.invite_code.clone()),
+                ("link", x.invite_link.clone()),
+            ];
+            (
+                self.text(locale, "ownership.field_name", &args),
+                self.text(locale, "ownership.field_value", &args),
+                false,
+            )
+        }));
+
+        Ok(Some(alert))
+    }
+
+    /// 許可リストに登録された鯖を除いた招待コードを取得する
+    async fn filter_allowlisted_invites(
+        &self,
+        post: &Post,
+        invites: &[DiscordInviteLink],
+    ) -> Result<Vec<DiscordInviteLink>> {
+        let invites = try_join_all(invites.iter().map(|invite| async move {
+            let record = match invite.guild_id {
+                Some(invite_guild_id) => {
+                    self.history
+                        .get_guild_list(&post.guild_id, &invite_guild_id)
+                        .await?
+                }
+                None => None,
+            };
+            // 許可リストに登録されていなければ残す
+            let allowed = matches!(record, Some(record) if record.kind == GuildListKind::Allow);
+            Ok::<_, Error>((!allowed).then(|| invite.clone()))
+        }))
+        .await?
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+        Ok(invites)
+    }
+
+    /// 招待先のメンバー数が足りているかを検証する
+    async fn check_invite_counts(
+        &self,
+        post: &Post,
+        invites: &[DiscordInviteLink],
+    ) -> Result<Option<Alert>> {
+        let required_member_count = self.app_config.discord.required_member_count;
+        let required_online_count = self.app_config.discord.required_online_count;
+        // メンバー数が足りない招待コードを集める
+        let small_invites = invites
+            .iter()
+            .filter(|x| {
+                x.member_count.unwrap_or(0) < required_member_count
+                    || x.presence_count.unwrap_or(0) < required_online_count
+            })
+            .collect::<Vec<_>>();
+        // メンバー数が足りている
+        if small_invites.is_empty() {
+            return Ok(None);
+        }
+
+        // 警告メッセージを構築
+        let locale = self.locale(post.channel_id, post.guild_id);
+        let mut alert = self.new_alert(
+            locale,
+            "member_count",
+            &[
+                ("members", required_member_count.to_string()),
+                ("online", required_online_count.to_string()),
+            ],
+        );
+        alert.embed.fields(small_invites.iter().map(|x| {
+            let args = [
+                ("code", x.invite_code.clone()),
+                ("members", x.member_count.unwrap_or(0).to_string()),
+                ("online", x.presence_count.unwrap_or(0).to_string()),
+            ];
+            (
+                self.text(locale, "member_count.field_name", &args),
+                self.text(locale, "member_count.field_value", &args),
+                false,
+            )
+        }));
+
+        Ok(Some(alert))
+    }
+
+    /// NSFWな鯖を宣伝していないか検証する
+    async fn check_invite_nsfw(
+        &self,
+        ctx: &Context,
+        post: &Post,
+        invites: &[DiscordInviteLink],
+    ) -> Result<Option<Alert>> {
+        // 設定で無効になっている
+        if !self.app_config.discord.block_nsfw {
+            return Ok(None);
+        }
+
+        // NSFWな鯖、またはNSFWかどうか確認できない鯖の招待コードを集める
+        let nsfw_invites = invites
+            .iter()
+            .filter(|x| x.is_nsfw() || x.is_nsfw_unknown())
+            .collect::<Vec<_>>();
+        if nsfw_invites.is_empty() {
+            return Ok(None);
+        }
+
+        // NSFWチャンネルであれば許可する
+        let channel = post
+            .channel_id
+            .to_channel(ctx)
+            .await
+            .context("チャンネル情報の取得に失敗")?;
+        if channel.is_nsfw() {
+            return Ok(None);
+        }
+
+        // 警告メッセージを構築
+        let locale = self.locale(post.channel_id, post.guild_id);
+        let mut alert = self.new_alert(locale, "nsfw", &[]);
+        alert.embed.fields(nsfw_invites.iter().map(|x| {
+            let args = [("code", x.invite_code.clone())];
+            let value_key = if x.is_nsfw_unknown() {
+                "nsfw.unknown_field_value"
+            } else {
+                "nsfw.field_value"
+            };
+            (
+                self.text(locale, "nsfw.field_name", &args),
+                self.text(locale, value_key, &args),
+                false,
+            )
+        }));
+
+        Ok(Some(alert))
+    }
+
     /// 過去ログに同じリンクがないかを検証
-    async fn check_invite_history<'t>(
+    async fn check_invite_history(
         &self,
         ctx: &Context,
-        msg: &Message,
+        post: &Post,
         invites: Vec<HistoryFindKey>,
-    ) -> Result<Option<Message>> {
+    ) -> Result<Option<Alert>> {
         // 過去ログに同じリンクがないかを検証
         type RecordLink = Vec<(HistoryRecord, String)>;
         let invites: Vec<Option<(HistoryFindKey, RecordLink)>> =
@@ -171,7 +623,7 @@ impl Handler {
                 // 履歴データベースから検索
                 let records = self
                     .history
-                    .validate(&msg.id, &msg.channel_id, &msg.author.id, &invite_key)
+                    .validate(&post.id, &post.channel_id, &post.author.id, &invite_key)
                     .await?;
 
                 let ban_period_user_start =
@@ -183,10 +635,10 @@ impl Handler {
                     .into_iter()
                     .map(|record| async {
                         // メッセージをDiscordから取得する
-                        let result = record.channel_id.message(ctx, record.message_id).await;
+                        let result = record.message_channel_id().message(ctx, record.message_id).await;
 
                         match result {
-                            Ok(message) if record.user_id == msg.author.id && record.timestamp > ban_period_user_start => {
+                            Ok(message) if record.user_id == post.author.id && record.timestamp > ban_period_user_start => {
                                 // min_per_user_start分以内のメッセージであれば前のメッセージを消す
                                 message.channel_id.delete_message(ctx, message.id).await?;
                                 Ok(None)
@@ -214,7 +666,7 @@ impl Handler {
                 .await?;
                 let records = records
                     .into_iter()
-                    .filter_map(|record| record)
+                    .flatten()
                     .collect::<Vec<HistoryRecord>>();
 
                 // 空だったらNoneを返す
@@ -226,7 +678,7 @@ impl Handler {
                 let records: RecordLink = join_all(records.into_iter().map(|record| async {
                     let invite_link = record
                         .message_id
-                        .link_ensured(ctx, record.channel_id, None)
+                        .link_ensured(ctx, record.message_channel_id(), None)
                         .await;
                     (record, invite_link)
                 }))
@@ -243,18 +695,15 @@ impl Handler {
             return Ok(None);
         }
 
-        let now = Utc::now().with_timezone(&Japan);
+        let locale = self.locale(post.channel_id, post.guild_id);
+        let now = Utc::now();
         // 直近の一番期限が遠いものを取得
         let invites_due = {
             let mut invites_due = invites
                 .iter()
                 .flat_map(move |(_invite_key, records)| records.iter())
                 .map(|(record, invite_link)| {
-                    let days = if record.user_id == msg.author.id {
-                        self.app_config.ban_period.day_per_user
-                    } else {
-                        self.app_config.ban_period.day
-                    };
+                    let days = self.cooldown_days(record, post.author.id);
                     let due =
                         NaiveDateTime::from_timestamp(record.timestamp, 0) + Duration::days(days);
                     (record, invite_link, due, days)
@@ -270,238 +719,849 @@ impl Handler {
         // 誰が宣伝したかを取得
         let who: Option<String> = match recent_sent {
             Some((record, _invite_link, _due, _days)) => {
-                if record.user_id == msg.author.id {
-                    Some("あなた".to_string())
+                if record.user_id == post.author.id {
+                    Some(self.text(locale, "recently_promoted.you", &[]))
                 } else {
                     let user = record.user_id.to_user(&ctx).await.ok();
-                    user.map(|user| format!("`{}`", user.name))
+                    user.map(|user| {
+                        self.text(locale, "recently_promoted.user", &[("name", user.name)])
+                    })
                 }
             }
             None => None,
         };
 
         // 警告メッセージを構築
-        let reply = msg
-            .channel_id
-            .send_message(ctx, |m| {
-                m.reference_message(msg);
-                m.content("残念、そのサーバーはしばらく宣伝できません。。。");
-                m.embed(|e| {
-                    e.title(format!("{0}最近宣伝された鯖は宣伝できません{0}", self.app_config.message.alert_emoji));
-                    e.description(format!("直近{}日間に他人が宣伝した鯖、及び直近{}日間に自分が宣伝した鯖は宣伝できません\n自分が宣伝した鯖は30分以内であれば再投稿できます", self.app_config.ban_period.day, self.app_config.ban_period.day_per_user));
-                    if let Some((record, _invite_link, due, days)) = recent_sent {
-                        let date: DateTime<Tz> = DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(record.timestamp, 0), Utc).with_timezone(&Japan);
-                        let who = who.unwrap_or("誰か".to_string());
-                        // 一番最新の宣伝
-                        e.field(
-                            format!("直近{days}日間に{who}がこのサーバーを宣伝しています"),
-                            format!(
-                                "{} ({}日前)に宣伝",
-                                date.format("%Y年%m月%d日 %H時%M分%S秒"),
-                                (now - date).num_days(),
-                            ),
-                            false,
+        let mut alert = self.new_alert(
+            locale,
+            "recently_promoted",
+            &[
+                ("day", self.app_config.ban_period.day.to_string()),
+                (
+                    "day_per_user",
+                    self.app_config.ban_period.day_per_user.to_string(),
+                ),
+                (
+                    "min_per_user_start",
+                    self.app_config.ban_period.min_per_user_start.to_string(),
+                ),
+            ],
+        );
+        if let Some((record, _invite_link, due, days)) = recent_sent {
+            let date =
+                DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(record.timestamp, 0), Utc);
+            let who = who.unwrap_or_else(|| self.text(locale, "recently_promoted.someone", &[]));
+            // 一番最新の宣伝
+            alert.embed.field(
+                self.text(
+                    locale,
+                    "recently_promoted.recent_name",
+                    &[("days", days.to_string()), ("who", who)],
+                ),
+                self.text(
+                    locale,
+                    "recently_promoted.recent_value",
+                    &[
+                        ("date", self.format_date(locale, &date)),
+                        ("days_ago", (now - date).num_days().to_string()),
+                    ],
+                ),
+                false,
+            );
+            // 履歴
+            alert.embed.field(
+                self.text(locale, "recently_promoted.history_name", &[]),
+                invites_due
+                    .iter()
+                    .map(|(record, invite_link, _due, _days)| {
+                        let date = DateTime::<Utc>::from_utc(
+                            NaiveDateTime::from_timestamp(record.timestamp, 0),
+                            Utc,
                         );
-                        // 履歴
-                        e.field(
-                            "以前に宣伝されたメッセージ",
-                            invites_due
-                                .iter()
-                                .map(|(record, invite_link, _due, _days)| {
-                                    let date: DateTime<Tz> = DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(record.timestamp, 0), Utc).with_timezone(&Japan);
-                                    let date_message = date.format("%Y年%m月%d日 %H時%M分%S秒");
-                                    if record.deleted {
-                                        format!("{}による削除済みの投稿 ({date_message})", record.user_id.mention())
-                                    } else {
-                                        format!("[メッセージリンク]({invite_link}) ({date_message})")
-                                    }
-                                })
-                                .collect::<Vec<_>>()
-                                .join("\n"),
-                            false,
-                        );
-                        // 期限
-                        let due_date: DateTime<Tz> = DateTime::<Utc>::from_utc(due.clone(), Utc).with_timezone(&Japan);
-                        e.field(
-                            "以下の日付を過ぎたら投稿可能です",
-                            format!(
-                                "{} ({}日後)に宣伝可能",
-                                due_date.format("%Y年%m月%d日 %H時%M分%S秒"),
-                                (due_date - now).num_days() + 1,
-                            ),
-                            false,
-                        );
-                    }
-                    e
-                })
+                        let date_message = self.format_date(locale, &date);
+                        if record.deleted {
+                            self.text(
+                                locale,
+                                "recently_promoted.history_deleted",
+                                &[
+                                    ("user", record.user_id.mention().to_string()),
+                                    ("date", date_message),
+                                ],
+                            )
+                        } else {
+                            self.text(
+                                locale,
+                                "recently_promoted.history_link",
+                                &[("link", invite_link.to_string()), ("date", date_message)],
+                            )
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                false,
+            );
+            // 期限
+            let due_date = DateTime::<Utc>::from_utc(*due, Utc);
+            alert.embed.field(
+                self.text(locale, "recently_promoted.due_name", &[]),
+                self.text(
+                    locale,
+                    "recently_promoted.due_value",
+                    &[
+                        ("date", self.format_date(locale, &due_date)),
+                        ("days_left", ((due_date - now).num_days() + 1).to_string()),
+                    ],
+                ),
+                false,
+            );
+            alert.due = Some(due_date);
+            alert.due_invite = Some((record.invite_guild_id, record.invite_code.clone()));
+        }
+
+        Ok(Some(alert))
+    }
+
+    /// 禁止ワードが含まれていないか検証する
+    async fn check_content_filter(
+        &self,
+        post: &Post,
+        finder: &InviteFinder,
+    ) -> Result<Option<Alert>> {
+        // 本文、埋め込み、添付ファイルなどすべての要素から探す
+        let word = match self.content_filter.find(&post.channel_id, finder.texts()) {
+            Some(word) => word,
+            None => return Ok(None),
+        };
+
+        // 警告メッセージを構築
+        let locale = self.locale(post.channel_id, post.guild_id);
+        let mut alert = self.new_alert(locale, "forbidden_word", &[]);
+        alert.embed.field(
+            self.text(locale, "forbidden_word.field_name", &[]),
+            self.text(locale, "forbidden_word.field_value", &[("word", word)]),
+            false,
+        );
+
+        Ok(Some(alert))
+    }
+
+    /// 禁止されたメンションを取り除いて再投稿するか (スレッド内はWebhookで投稿できないため拒否する)
+    fn sanitizes_mentions(&self, post: &Post) -> bool {
+        self.app_config.mentions.action == MentionAction::Sanitize && post.thread_id.is_none()
+    }
+
+    /// 禁止されたメンションを探す (メッセージのキー, 値)
+    fn find_blocked_mentions(&self, post: &Post) -> Vec<(&'static str, Vec<(&str, String)>)> {
+        let config = &self.app_config.mentions;
+        let mut blocked = vec![];
+
+        // 権限がなく通知されなかった場合も本文に含まれていれば禁止する
+        if config.block_everyone
+            && (post.mention_everyone
+                || post.content.contains("@everyone")
+                || post.content.contains("@here"))
+        {
+            blocked.push(("mention.everyone", vec![]));
+        }
+        if config.block_roles && !post.mention_roles.is_empty() {
+            blocked.push((
+                "mention.roles",
+                vec![("count", post.mention_roles.len().to_string())],
+            ));
+        }
+        if let Some(max) = config.max_user_mentions {
+            if post.mention_users > max {
+                blocked.push((
+                    "mention.users",
+                    vec![
+                        ("count", post.mention_users.to_string()),
+                        ("max", max.to_string()),
+                    ],
+                ));
+            }
+        }
+
+        blocked
+    }
+
+    /// 禁止されたメンションが含まれていないか検証する (取り除いて再投稿する設定の場合は警告しない)
+    async fn check_mentions(&self, post: &Post) -> Result<Option<Alert>> {
+        if self.sanitizes_mentions(post) {
+            return Ok(None);
+        }
+        let blocked = self.find_blocked_mentions(post);
+        if blocked.is_empty() {
+            return Ok(None);
+        }
+
+        // 警告メッセージを構築
+        let locale = self.locale(post.channel_id, post.guild_id);
+        let mut alert = self.new_alert(locale, "mention", &[]);
+        alert.embed.field(
+            self.text(locale, "mention.field_name", &[]),
+            blocked
+                .iter()
+                .map(|(key, args)| self.text(locale, key, args))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            false,
+        );
+
+        Ok(Some(alert))
+    }
+
+    /// Discord以外のリンクが許可されているか検証する
+    async fn check_link_policy(&self, post: &Post, finder: &InviteFinder) -> Result<Option<Alert>> {
+        let config = &self.app_config.link_policy;
+        let links = finder.external_links()?;
+        let locale = self.locale(post.channel_id, post.guild_id);
+
+        // 禁止されたドメイン、または許可されていないドメインのリンクを集める
+        let denied_links = links
+            .iter()
+            .filter(|link| {
+                let denied = config
+                    .deny_domains
+                    .iter()
+                    .any(|domain| domain_matches(&link.domain, domain));
+                let allowed = config.allow_domains.is_empty()
+                    || config
+                        .allow_domains
+                        .iter()
+                        .any(|domain| domain_matches(&link.domain, domain));
+                denied || !allowed
             })
-            .await
-            .context("警告メッセージの構築に失敗")?;
+            .collect::<Vec<_>>();
+        if !denied_links.is_empty() {
+            // 警告メッセージを構築
+            let mut alert = self.new_alert(locale, "denied_link", &[]);
+            alert.embed.fields(denied_links.iter().map(|link| {
+                let args = [
+                    ("domain", link.domain.clone()),
+                    ("url", link.url.clone()),
+                    ("source", self.text(locale, link.source.message_key(), &[])),
+                ];
+                (
+                    self.text(locale, "denied_link.field_name", &args),
+                    self.text(locale, "denied_link.field_value", &args),
+                    false,
+                )
+            }));
+            return Ok(Some(alert));
+        }
 
-        Ok(Some(reply))
+        // リンクの数を検証
+        match config.max_external_links {
+            Some(max) if links.len() > max => {
+                // 警告メッセージを構築
+                let alert = self.new_alert(
+                    locale,
+                    "too_many_links",
+                    &[("max", max.to_string()), ("count", links.len().to_string())],
+                );
+                Ok(Some(alert))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// 投稿の長さと書式が上限を超えていないか検証する
+    async fn check_message_limits(
+        &self,
+        post: &Post,
+        finder: &InviteFinder,
+    ) -> Result<Option<Alert>> {
+        let limits = &self.app_config.limits;
+        let locale = self.locale(post.channel_id, post.guild_id);
+
+        // 見出し (# 見出し) の行を数えるパターン
+        let header_regex =
+            Regex::new(r"^\s*#{1,3}\s+\S").context("正規表現のパターンの作成に失敗")?;
+
+        // (上限, 実際の値, メッセージのセクション)
+        let rules = [
+            (limits.max_length, post.content.chars().count(), "too_long"),
+            (
+                limits.max_lines,
+                post.content.lines().count(),
+                "too_many_lines",
+            ),
+            (
+                limits.max_headers,
+                post.content
+                    .lines()
+                    .filter(|line| header_regex.is_match(line))
+                    .count(),
+                "too_many_headers",
+            ),
+            (
+                limits.max_invites,
+                finder.invite_codes.len(),
+                "too_many_invites",
+            ),
+        ];
+        // 最初に上限を超えたものを警告する
+        let exceeded = rules
+            .into_iter()
+            .find(|(max, count, _section)| *max > 0 && count > max);
+        let (max, count, section) = match exceeded {
+            Some(exceeded) => exceeded,
+            None => return Ok(None),
+        };
+
+        // 警告メッセージを構築
+        let alert = self.new_alert(
+            locale,
+            section,
+            &[("max", max.to_string()), ("count", count.to_string())],
+        );
+
+        Ok(Some(alert))
     }
 
     /// 説明文が書かれているかどうかを検証する
-    async fn check_invite_message<'t>(
+    async fn check_invite_message(&self, post: &Post, description: &str) -> Result<Option<Alert>> {
+        // 説明文を評価 (招待リンクや装飾を除いた長さ、単語数)
+        let score = description::score(description)?;
+        let locale = self.locale(post.channel_id, post.guild_id);
+
+        // 長さが足りているかどうかを検証
+        let required_length = self.app_config.discord.required_message_length;
+        if score.length <= required_length {
+            // 警告メッセージを構築
+            let alert = self.new_alert(
+                locale,
+                "short_description",
+                &[("length", required_length.to_string())],
+            );
+            return Ok(Some(alert));
+        }
+
+        // 単語数が足りているかどうかを検証
+        let required_words = self.app_config.discord.required_distinct_words;
+        if score.distinct_words < required_words {
+            // 警告メッセージを構築
+            let alert = self.new_alert(
+                locale,
+                "few_words",
+                &[("words", required_words.to_string())],
+            );
+            return Ok(Some(alert));
+        }
+
+        Ok(None)
+    }
+
+    /// 最近の投稿と説明文が似ていないか検証する
+    async fn check_duplicate_description(
         &self,
         ctx: &Context,
-        msg: &Message,
-        finder: &InviteFinder<'t>,
-    ) -> Result<Option<Message>> {
-        // リンクの合計の長さを取得
-        let link_total_length = finder
-            .invite_codes
-            .iter()
-            .map(|invite_link| invite_link.invite_link.chars().count())
-            .sum::<usize>();
-        // メッセージを全体の長さを取得
-        let message_length = msg.content.chars().count();
-        // 説明文の長さを計算
-        let desc_length = message_length - link_total_length;
-        // 長さが足りているかどうかを検証
-        if desc_length > self.app_config.discord.required_message_length {
+        post: &Post,
+        invites: &[DiscordInviteLink],
+        fingerprint: &str,
+    ) -> Result<Option<Alert>> {
+        let config = &self.app_config.duplicate;
+        // 設定で無効になっている、または比較するには短すぎる
+        if config.threshold <= 0.0 || fingerprint.chars().count() < config.min_length {
             return Ok(None);
         }
 
+        // 最近の投稿の説明文を取得 (同じ鯖の再投稿で削除される投稿は比べない)
+        let since = (Utc::now() - Duration::days(config.days)).timestamp();
+        let records = self
+            .history
+            .get_descriptions(
+                &post.id,
+                &post.guild_id,
+                since,
+                &Self::repost_key(post, invites),
+            )
+            .await?;
+
+        // 一番似ている投稿を探す
+        let similar = records
+            .iter()
+            .map(|record| {
+                (
+                    record,
+                    description::similarity(fingerprint, &record.fingerprint),
+                )
+            })
+            .filter(|(_record, similarity)| *similarity >= config.threshold)
+            .max_by(|(_, a), (_, b)| a.total_cmp(b));
+        let (record, similarity) = match similar {
+            Some(similar) => similar,
+            None => return Ok(None),
+        };
+
         // 警告メッセージを構築
-        let reply = msg
-            .channel_id
-            .send_message(ctx, |m| {
-                m.reference_message(msg);
-                m.content(format!("説明を追加してサーバーをアピールしましょう！\n{}文字以上説明文を書いて再度投稿してね\nがんばれ！", self.app_config.discord.required_message_length));
-                m.embed(|e| {
-                    e.title(format!("{0}説明文が足りません{0}", self.app_config.message.alert_emoji));
-                    e.description(
-                        format!(
-                            "説明文の長さが短すぎます\n少なくとも{}文字は説明文が必要です",
-                            self.app_config.discord.required_message_length,
-                        ),
+        let locale = self.locale(post.channel_id, post.guild_id);
+        let link = record
+            .message_id
+            .link_ensured(ctx, record.channel_id, record.guild_id)
+            .await;
+        let date =
+            DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(record.timestamp, 0), Utc);
+        let mut alert = self.new_alert(locale, "duplicate", &[]);
+        alert.embed.field(
+            self.text(locale, "duplicate.field_name", &[]),
+            self.text(
+                locale,
+                "duplicate.field_value",
+                &[
+                    ("link", link),
+                    ("date", self.format_date(locale, &date)),
+                    ("similarity", format!("{:.0}", similarity * 100.0)),
+                ],
+            ),
+            false,
+        );
+
+        Ok(Some(alert))
+    }
+
+    /// アカウントの作成日数とサーバーの参加時間を検証する
+    async fn check_account_age(&self, ctx: &Context, post: &Post) -> Result<Option<Alert>> {
+        let rule = self.app_config.account_age.rule(&post.channel_id);
+        let locale = self.locale(post.channel_id, post.guild_id);
+
+        // アカウントの作成日数を検証
+        if rule.min_account_days > 0 {
+            let created_at = DateTime::<Utc>::from_utc(
+                NaiveDateTime::from_timestamp(post.author.created_at().unix_timestamp(), 0),
+                Utc,
+            );
+            let due = created_at + Duration::days(rule.min_account_days);
+            if Utc::now() < due {
+                // 警告メッセージを構築
+                let mut alert = self.new_alert(
+                    locale,
+                    "account_age",
+                    &[("days", rule.min_account_days.to_string())],
+                );
+                alert.embed.field(
+                    self.text(locale, "account_age.due_name", &[]),
+                    self.text(
+                        locale,
+                        "account_age.due_value",
+                        &[("date", self.format_date(locale, &due))],
+                    ),
+                    false,
+                );
+                return Ok(Some(alert));
+            }
+        }
+
+        // サーバーの参加時間を検証
+        if let (true, Some(guild_id)) = (rule.min_member_hours > 0, post.guild_id) {
+            // メッセージに含まれていなければメンバー情報を取得する
+            let joined_at = match post.joined_at {
+                Some(joined_at) => Some(joined_at),
+                None => {
+                    guild_id
+                        .member(ctx, post.author.id)
+                        .await
+                        .context("メンバー情報の取得に失敗")?
+                        .joined_at
+                }
+            };
+            if let Some(joined_at) = joined_at {
+                let joined_at = DateTime::<Utc>::from_utc(
+                    NaiveDateTime::from_timestamp(joined_at.unix_timestamp(), 0),
+                    Utc,
+                );
+                let due = joined_at + Duration::hours(rule.min_member_hours);
+                if Utc::now() < due {
+                    // 警告メッセージを構築
+                    let mut alert = self.new_alert(
+                        locale,
+                        "member_tenure",
+                        &[("hours", rule.min_member_hours.to_string())],
                     );
-                    e.field(
-                        format!("投稿を{}秒以内にコピーしてください！", self.app_config.discord.alert_sec),
-                        format!("あなたの投稿は{}秒後に削除されます。メッセージの編集機能は使用せずメモ帳などにコピーして修正後、再投稿してください", self.app_config.discord.alert_sec),
-                        false
+                    alert.embed.field(
+                        self.text(locale, "member_tenure.due_name", &[]),
+                        self.text(
+                            locale,
+                            "member_tenure.due_value",
+                            &[("date", self.format_date(locale, &due))],
+                        ),
+                        false,
                     );
-                    e
-                })
-            })
-            .await
-            .context("警告メッセージの構築に失敗")?;
+                    return Ok(Some(alert));
+                }
+            }
+        }
 
-        Ok(Some(reply))
+        Ok(None)
     }
 
-    /// 招待リンクが含まれるか検証する
-    async fn check_has_invite<'t>(
+    /// ユーザーの宣伝回数が上限を超えていないか検証する
+    async fn check_user_quota(
         &self,
-        ctx: &Context,
-        msg: &Message,
-        finder: &InviteFinder<'t>,
-    ) -> Result<Option<Message>> {
+        post: &Post,
+        invites: &[DiscordInviteLink],
+    ) -> Result<Option<Alert>> {
+        let config = &self.app_config.quota;
+        // 設定で無効になっている
+        if config.posts == 0 {
+            return Ok(None);
+        }
+
+        // 期間内の投稿を取得
+        // (同じ鯖の再投稿で削除される投稿は数えない)
+        let since = (Utc::now() - Duration::hours(config.window_hours)).timestamp();
+        let timestamps = self
+            .history
+            .get_post_timestamps_by_user(
+                &post.id,
+                &post.guild_id,
+                config.per_channel.then_some(&post.channel_id),
+                since,
+                &Self::repost_key(post, invites),
+            )
+            .await?;
+        // 上限に達していない
+        let posts = config.posts as usize;
+        if timestamps.len() < posts {
+            return Ok(None);
+        }
+
+        // 上限を下回るのは、新しい方から数えて上限番目の投稿が期間外になった時
+        let due = DateTime::<Utc>::from_utc(
+            NaiveDateTime::from_timestamp(timestamps[timestamps.len() - posts], 0),
+            Utc,
+        ) + Duration::hours(config.window_hours);
+
+        // 警告メッセージを構築
+        let locale = self.locale(post.channel_id, post.guild_id);
+        let mut alert = self.new_alert(
+            locale,
+            "quota",
+            &[
+                ("posts", config.posts.to_string()),
+                ("hours", config.window_hours.to_string()),
+            ],
+        );
+        alert.embed.field(
+            self.text(locale, "quota.due_name", &[]),
+            self.text(
+                locale,
+                "quota.due_value",
+                &[("date", self.format_date(locale, &due))],
+            ),
+            false,
+        );
+        alert.due = Some(due);
+
+        Ok(Some(alert))
+    }
+
+    /// 再投稿で置き換えられる投稿を探すキーを作成する
+    fn repost_key(post: &Post, invites: &[DiscordInviteLink]) -> RepostKey {
+        let invite_codes = invites
+            .iter()
+            .map(|invite| HistoryFindKey::InviteCode(invite.invite_code.clone()));
+        let invite_guilds = invites
+            .iter()
+            .filter_map(|invite| invite.guild_id)
+            .map(HistoryFindKey::InviteGuildId);
+        RepostKey {
+            channel_id: post.channel_id,
+            user_id: post.author.id,
+            invites: invite_codes.chain(invite_guilds).collect(),
+        }
+    }
+
+    /// 招待リンクが含まれるか検証する
+    async fn check_has_invite(&self, post: &Post, finder: &InviteFinder) -> Result<Option<Alert>> {
         // 招待リンクが含まれるか検証する
         if !finder.invite_codes.is_empty() {
             return Ok(None);
         }
 
         // 警告メッセージを構築
-        let reply = msg
-            .channel_id
-            .send_message(ctx, |m| {
-                m.reference_message(msg);
-                m.content("Discordサーバーの招待リンクを投稿しましょう！\n以下の手順で招待リンクを作成して再度投稿してね");
-                m.embed(|e| {
-                    e.title(format!("{0}Discord鯖の宣伝のみ許可されています{0}", self.app_config.message.alert_emoji));
-                    e.description(format!("ここはDiscord鯖の宣伝する為のチャンネルです\n少なくとも1つ以上のDiscord招待リンクが必要です\n招待リンクの作り方は[こちらをクリック！]({})", self.app_config.message.no_expiration_invite_link_guide));
-                    e.field(
-                        format!("投稿を{}秒以内にコピーしてください！", self.app_config.discord.alert_sec),
-                        format!("あなたの投稿は{}秒後に削除されます。メッセージの編集機能は使用せずメモ帳などにコピーして修正後、再投稿してください", self.app_config.discord.alert_sec),
-                        false
-                    );
-                    e
-                })
-            })
-            .await
-            .context("警告メッセージの構築に失敗")?;
+        let locale = self.locale(post.channel_id, post.guild_id);
+        let alert = self.new_alert(locale, "no_invite", &[]);
 
-        Ok(Some(reply))
+        Ok(Some(alert))
     }
 
-    /// 招待メッセージの検証をすべて実行する
-    async fn check_invite<'t>(&self, ctx: &Context, msg: &Message) -> Result<Option<Message>> {
-        // 招待リンクをパース
-        let finder = InviteFinder::new(msg.content.as_str())?;
-
+    /// 投稿内容の検証をすべて実行する (メッセージ以外から投稿する場合も共通)
+    async fn check_post(
+        &self,
+        ctx: &Context,
+        post: &Post,
+        finder: &InviteFinder,
+    ) -> Result<Verdict> {
         // メッセージに招待リンクが含まれているか検証
-        match self
-            .check_has_invite(ctx, msg, &finder)
+        if let Some(alert) = self
+            .check_has_invite(post, finder)
             .await
             .context("招待リンクが含むかの検証に失敗")?
         {
-            Some(reply) => return Ok(Some(reply)),
-            None => (), // 検証に失敗
-        };
+            return Ok(Verdict::Alert(alert));
+        }
 
-        // メッセージを検証
-        match self
-            .check_invite_message(ctx, msg, &finder)
+        // アカウントの作成日数とサーバーの参加時間を検証
+        if let Some(alert) = self
+            .check_account_age(ctx, post)
             .await
-            .context("メッセージ長さの検証に失敗")?
+            .context("アカウントの作成日数の検証に失敗")?
         {
-            Some(reply) => return Ok(Some(reply)),
-            None => (), // 検証に失敗
-        };
+            return Ok(Verdict::Alert(alert));
+        }
 
-        // メッセージが過去に送信された招待リンクを検証 (招待リンク)
-        let invite_codes = finder
-            .invite_codes
-            .clone()
-            .into_iter()
-            .map(|f| HistoryFindKey::InviteCode(f.invite_code.to_string()))
-            .collect::<Vec<_>>();
-        match self
-            .check_invite_history(ctx, msg, invite_codes)
+        // 禁止ワードを検証
+        if let Some(alert) = self
+            .check_content_filter(post, finder)
             .await
-            .context("過去の招待コードの検証に失敗")?
+            .context("禁止ワードの検証に失敗")?
         {
-            Some(reply) => return Ok(Some(reply)),
-            None => (), // 検証に失敗
-        };
+            return Ok(Verdict::Alert(alert));
+        }
+
+        // メンションを検証
+        if let Some(alert) = self
+            .check_mentions(post)
+            .await
+            .context("メンションの検証に失敗")?
+        {
+            return Ok(Verdict::Alert(alert));
+        }
+
+        // Discord以外のリンクを検証
+        if let Some(alert) = self
+            .check_link_policy(post, finder)
+            .await
+            .context("リンクの検証に失敗")?
+        {
+            return Ok(Verdict::Alert(alert));
+        }
+
+        // 投稿の長さと書式を検証
+        if let Some(alert) = self
+            .check_message_limits(post, finder)
+            .await
+            .context("投稿の長さと書式の検証に失敗")?
+        {
+            return Ok(Verdict::Alert(alert));
+        }
+
+        // メッセージを検証
+        let description = finder.description();
+        if let Some(alert) = self
+            .check_invite_message(post, &description)
+            .await
+            .context("メッセージ長さの検証に失敗")?
+        {
+            return Ok(Verdict::Alert(alert));
+        }
 
         // 招待コードリストを取得
         let invites = finder
-            .get_invite_list()
+            .get_invite_list(&ctx.http)
             .await
             .context("招待リンク情報の取得に失敗")?;
 
+        // 最近の投稿と説明文が似ていないか検証
+        let fingerprint = description::fingerprint(&description);
+        if let Some(alert) = self
+            .check_duplicate_description(ctx, post, &invites, &fingerprint)
+            .await
+            .context("説明文の重複の検証に失敗")?
+        {
+            return Ok(Verdict::Alert(alert));
+        }
+
+        // ユーザーの宣伝回数を検証
+        if let Some(alert) = self
+            .check_user_quota(post, &invites)
+            .await
+            .context("宣伝回数の検証に失敗")?
+        {
+            return Ok(Verdict::Alert(alert));
+        }
+
         // 招待コードを検証
-        match self
-            .check_invite_links(ctx, msg, &invites)
+        if let Some(alert) = self
+            .check_invite_links(post, &invites)
             .await
             .context("招待コード期限の検証に失敗")?
         {
-            Some(reply) => return Ok(Some(reply)),
-            None => (), // 検証に失敗
-        };
+            return Ok(Verdict::Alert(alert));
+        }
+
+        // 禁止リストに登録された鯖でないか検証
+        if let Some(alert) = self
+            .check_invite_denylist(post, &invites)
+            .await
+            .context("禁止リストの検証に失敗")?
+        {
+            return Ok(Verdict::Alert(alert));
+        }
+
+        // 招待先のメンバー数を検証
+        if let Some(alert) = self
+            .check_invite_counts(post, &invites)
+            .await
+            .context("招待先のメンバー数の検証に失敗")?
+        {
+            return Ok(Verdict::Alert(alert));
+        }
+
+        // NSFWな鯖でないか検証
+        if let Some(alert) = self
+            .check_invite_nsfw(ctx, post, &invites)
+            .await
+            .context("NSFWの検証に失敗")?
+        {
+            return Ok(Verdict::Alert(alert));
+        }
+
+        // 宣伝するサーバーに所属しているか検証
+        if let Some(alert) = self
+            .check_invite_ownership(post, &invites)
+            .await
+            .context("所属確認の検証に失敗")?
+        {
+            return Ok(Verdict::Alert(alert));
+        }
+
+        // 過去の宣伝からのクールダウンを検証
+        if let Some(alert) = self.check_cooldown(ctx, post, &invites).await? {
+            return Ok(Verdict::Alert(alert));
+        }
+
+        Ok(Verdict::Pass {
+            invites,
+            fingerprint,
+        })
+    }
+
+    /// 過去の宣伝からのクールダウンを検証する (許可リストに登録された鯖は除く)
+    async fn check_cooldown(
+        &self,
+        ctx: &Context,
+        post: &Post,
+        invites: &[DiscordInviteLink],
+    ) -> Result<Option<Alert>> {
+        // 許可リストに登録された鯖はクールダウンの検証をしない
+        let cooldown_invites = self
+            .filter_allowlisted_invites(post, invites)
+            .await
+            .context("許可リストの検証に失敗")?;
+
+        // メッセージが過去に送信された招待リンクを検証 (招待リンク)
+        let invite_codes = cooldown_invites
+            .iter()
+            .map(|f| HistoryFindKey::InviteCode(f.invite_code.to_string()))
+            .collect::<Vec<_>>();
+        if let Some(alert) = self
+            .check_invite_history(ctx, post, invite_codes)
+            .await
+            .context("過去の招待コードの検証に失敗")?
+        {
+            return Ok(Some(alert));
+        }
 
         // メッセージが過去に送信された招待リンクを検証 (ギルドID)
-        let invite_guilds = invites
-            .clone()
+        let invite_guilds = cooldown_invites
             .into_iter()
             .filter_map(|f| f.guild_id)
             .map(HistoryFindKey::InviteGuildId)
             .collect::<Vec<_>>();
-        match self
-            .check_invite_history(ctx, msg, invite_guilds)
+        if let Some(alert) = self
+            .check_invite_history(ctx, post, invite_guilds)
             .await
             .context("過去の招待サーバーの検証に失敗")?
         {
-            Some(reply) => return Ok(Some(reply)),
-            None => (), // 検証に失敗
+            return Ok(Some(alert));
+        }
+
+        Ok(None)
+    }
+
+    /// 招待メッセージの検証をすべて実行する
+    async fn check_invite(
+        &self,
+        ctx: &Context,
+        msg: &Message,
+        post: &Post,
+    ) -> Result<Option<Alert>> {
+        // 転送されたメッセージ (返信ではない参照付きメッセージ) を取得
+        let forwarded = match &msg.message_reference {
+            Some(reference) if msg.kind == MessageType::Regular => match reference.message_id {
+                Some(message_id) => reference.channel_id.message(ctx, message_id).await.ok(),
+                None => None,
+            },
+            _ => None,
+        };
+
+        // 招待リンクをパース (本文、埋め込み、添付ファイル、フォーラムの投稿タイトルなどすべての要素から探す)
+        let mut finder = InviteFinder::from_message(msg, forwarded.as_ref(), post.title.as_deref())
+            .await
+            .context("招待リンクの検索に失敗")?;
+        // 短縮URLのリダイレクト先からも招待リンクを探す
+        finder
+            .resolve_shortened_links(&self.app_config.shortener)
+            .await
+            .context("短縮URLの解決に失敗")?;
+
+        // 投稿内容を検証
+        let (invites, fingerprint) = match self
+            .check_post(ctx, post, &finder)
+            .await
+            .context("投稿内容の検証に失敗")?
+        {
+            Verdict::Alert(alert) => return Ok(Some(alert)),
+            Verdict::Pass {
+                invites,
+                fingerprint,
+            } => (invites, fingerprint),
         };
 
+        // 承認制のチャンネルの場合、投稿を削除してスタッフの承認待ちに追加する
+        if self.requires_approval(post) {
+            self.queue_for_approval(ctx, msg, post, &invites, fingerprint)
+                .await
+                .context("承認待ちへの追加に失敗")?;
+            return Ok(None);
+        }
+
+        // 禁止されたメンションを取り除く設定の場合、メンションを無効化して投稿者の名前で再投稿する
+        let (thread_id, message_id) =
+            if self.sanitizes_mentions(post) && !self.find_blocked_mentions(post).is_empty() {
+                let repost = Repost::from_message(msg, msg.content_safe(ctx));
+                let posted = self
+                    .repost_as_author(ctx, post.channel_id, &repost)
+                    .await
+                    .context("メンションを取り除いた再投稿に失敗")?;
+                msg.delete(ctx)
+                    .await
+                    .context("メンションを含む投稿の削除に失敗")?;
+                (None, posted.id)
+            } else {
+                (post.thread_id, post.id)
+            };
+
         // 警告がない場合、履歴に登録
+        self.record_history(post, thread_id, message_id, &invites, fingerprint)
+            .await?;
+
+        Ok(None)
+    }
+
+    /// 宣伝を履歴に登録する
+    async fn record_history(
+        &self,
+        post: &Post,
+        thread_id: Option<ChannelId>,
+        message_id: MessageId,
+        invites: &[DiscordInviteLink],
+        fingerprint: String,
+    ) -> Result<()> {
         self.history
-            .delete(&msg.id)
+            .delete(&post.id)
             .await
             .context("履歴の更新に失敗")?;
         let invite_result = invites.iter().map(|invite| async {
@@ -513,11 +1573,12 @@ impl Handler {
                     .insert(HistoryRecord {
                         invite_code: invite.invite_code.to_string(),
                         invite_guild_id: guild_id,
-                        guild_id: msg.guild_id,
-                        channel_id: msg.channel_id,
-                        message_id: msg.id,
-                        user_id: msg.author.id,
-                        timestamp: msg.timestamp.unix_timestamp(), // 現在の時間
+                        guild_id: post.guild_id,
+                        channel_id: post.channel_id,
+                        thread_id,
+                        message_id,
+                        user_id: post.author.id,
+                        timestamp: post.timestamp.unix_timestamp(), // 現在の時間
                         deleted: false,
                     })
                     .await;
@@ -527,16 +1588,70 @@ impl Handler {
         try_join_all(invite_result)
             .await
             .context("履歴の登録に失敗")?;
+        // 説明文を履歴に登録
+        self.history
+            .insert_description(DescriptionRecord {
+                guild_id: post.guild_id,
+                channel_id: thread_id.unwrap_or(post.channel_id),
+                message_id,
+                user_id: post.author.id,
+                fingerprint,
+                timestamp: post.timestamp.unix_timestamp(),
+            })
+            .await
+            .context("説明文の履歴の登録に失敗")?;
 
-        Ok(None)
+        Ok(())
+    }
+
+    /// 期限の来たリマインダーと投稿権限の復元を定期的に処理する (Botが停止するまで終わらない)
+    async fn run_timers(&self, ctx: &Context) {
+        // 再接続で何度呼ばれても1つだけ動かす
+        if self.timers_running.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        loop {
+            if let Err(why) = self.send_due_reminders(ctx).await {
+                error!("リマインダーの送信に失敗: {:?}", why);
+            }
+            if let Err(why) = self.restore_expired_access(ctx).await {
+                error!("投稿権限の復元に失敗: {:?}", why);
+            }
+            sleep(tokio::time::Duration::from_secs(TIMER_INTERVAL_SECS)).await;
+        }
     }
 }
 
 #[async_trait]
 impl EventHandler for Handler {
     /// 準備完了時に呼ばれる
-    async fn ready(&self, _ctx: Context, data_about_bot: Ready) {
+    async fn ready(&self, ctx: Context, data_about_bot: Ready) {
         warn!("Bot準備完了: {}", data_about_bot.user.tag());
+
+        // スラッシュコマンドを登録
+        if let Err(why) = self.register_commands(&ctx).await {
+            error!("スラッシュコマンドの登録に失敗: {:?}", why);
+        }
+
+        // 期限を過ぎたリマインダーの送信と投稿権限の復元を開始
+        self.run_timers(&ctx).await;
+    }
+
+    /// インタラクションが作成された時に呼ばれる
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        let result = match interaction {
+            Interaction::ApplicationCommand(command) => {
+                self.on_application_command(&ctx, &command).await
+            }
+            Interaction::MessageComponent(component) => {
+                self.on_message_component(&ctx, &component).await
+            }
+            Interaction::ModalSubmit(modal) => self.on_modal_submit(&ctx, &modal).await,
+            _ => Ok(()),
+        };
+        if let Err(why) = result {
+            error!("インタラクションの処理に失敗: {:?}", why);
+        }
     }
 
     /// メッセージが送信された時に呼び出される
@@ -546,10 +1661,19 @@ impl EventHandler for Handler {
             return;
         }
 
-        // コンフィグで指定されたチャンネルのメッセージのみ処理する
-        if !self.app_config.discord.channels.contains(&msg.channel_id) {
-            return; // チャンネルが違う
-        }
+        // コンフィグで指定されたチャンネル (とその中のスレッド、フォーラムの投稿) のメッセージのみ処理する
+        let (channel_id, thread) = match self
+            .resolve_post_channel(&ctx, msg.channel_id, msg.guild_id)
+            .await
+        {
+            Ok(Some(channel)) => channel,
+            Ok(None) => return, // チャンネルが違う
+            Err(why) => {
+                error!("チャンネルの判定に失敗: {:?}", why);
+                return;
+            }
+        };
+        let post = Post::from_message(&msg, channel_id, thread.as_ref());
 
         // 無視するロールを持っているかどうかを検証
         let manage_channels = msg.member.as_ref().map(|member| {
@@ -563,9 +1687,9 @@ impl EventHandler for Handler {
             return;
         }
 
-        // チェック&警告
-        let reply = match self.check_invite(&ctx, &msg).await {
-            Ok(Some(reply)) => reply, // 警告あり
+        // チェック
+        let alert = match self.check_invite(&ctx, &msg, &post).await {
+            Ok(Some(alert)) => alert, // 警告あり
             Ok(None) => return,       // 警告なし
             Err(why) => {
                 // エラー
@@ -574,8 +1698,25 @@ impl EventHandler for Handler {
             }
         };
 
+        // 違反を記録し、繰り返し違反していれば処分する
+        if let Err(why) = self.apply_strike(&ctx, &post, &alert).await {
+            error!("違反の処分に失敗: {:?}", why);
+        }
+
+        // 警告
+        let replies = match self.send_alert(&ctx, &msg, &post, alert).await {
+            Ok(replies) => replies,
+            Err(why) => {
+                error!("警告メッセージの送信に失敗: {:?}", why);
+                return;
+            }
+        };
+
         // 一定時間後に警告メッセージを削除
-        if let Err(why) = self.wait_and_delete_message(&ctx, &msg, &reply).await {
+        if let Err(why) = self
+            .wait_and_delete_message(&ctx, &msg, &post, &replies)
+            .await
+        {
             error!("警告メッセージの削除に失敗: {:?}", why);
             return;
         }
@@ -669,7 +1810,7 @@ impl EventHandler for Handler {
         match try_join_all(records.iter().map(|record| async {
             // Discordのメッセージを削除
             record
-                .channel_id
+                .message_channel_id()
                 .delete_message(&ctx, record.message_id)
                 .await?;
             // レコードを削除
@@ -0,0 +1,375 @@
+use anyhow::{Context as _, Result};
+use chrono::Utc;
+use futures::future::try_join_all;
+use log::warn;
+use serenity::builder::CreateEmbed;
+use serenity::model::{
+    channel::{Attachment, AttachmentType, Message},
+    id::GuildId,
+    interactions::{
+        message_component::{ButtonStyle, MessageComponentInteraction},
+        InteractionResponseType,
+    },
+    Timestamp,
+};
+use serenity::prelude::*;
+
+use super::{Alert, Handler, Post, Repost};
+use crate::history_log::{ApprovalRecord, DescriptionRecord, HistoryRecord};
+use crate::invite_finder::DiscordInviteLink;
+
+/// 承認ボタンのカスタムID
+pub(super) const APPROVE_BUTTON_ID: &str = "approval_approve";
+/// 却下ボタンのカスタムID
+pub(super) const REJECT_BUTTON_ID: &str = "approval_reject";
+/// 埋め込みのフィールドに表示できる最大文字数
+const MAX_FIELD_LENGTH: usize = 1024;
+
+/// 埋め込みのフィールドに収まるよう文字列を切り詰める
+pub(super) fn truncate_field(text: &str) -> String {
+    if text.chars().count() <= MAX_FIELD_LENGTH {
+        return text.to_string();
+    }
+    let mut truncated = text.chars().take(MAX_FIELD_LENGTH - 1).collect::<String>();
+    truncated.push('…');
+    truncated
+}
+
+impl Handler {
+    /// スタッフの承認が必要な投稿か (スレッド内はWebhookで投稿できないため対象外)
+    pub(super) fn requires_approval(&self, post: &Post) -> bool {
+        let config = &self.app_config.approval;
+        config.review_channel.is_some()
+            && post.thread_id.is_none()
+            && config.channels.contains(&post.channel_id)
+    }
+
+    /// 投稿を削除してスタッフの承認待ちに追加する
+    pub(super) async fn queue_for_approval(
+        &self,
+        ctx: &Context,
+        msg: &Message,
+        post: &Post,
+        invites: &[DiscordInviteLink],
+        fingerprint: String,
+    ) -> Result<()> {
+        let review_channel = match self.app_config.approval.review_channel {
+            Some(review_channel) => review_channel,
+            None => return Ok(()),
+        };
+        // 元の投稿を削除すると添付ファイルも消えるため、承認待ちメッセージに添付し直す
+        let attachments = try_join_all(msg.attachments.iter().map(|attachment| async move {
+            let data = attachment
+                .download()
+                .await
+                .with_context(|| format!("添付ファイルのダウンロードに失敗: {}", attachment.url))?;
+            Ok::<_, anyhow::Error>(AttachmentType::Bytes {
+                data: data.into(),
+                filename: attachment.filename.clone(),
+            })
+        }))
+        .await?;
+        // 招待リンクの一覧 (短縮URLや掲示板のリンクは投稿されたまま表示する)
+        let links = invites
+            .iter()
+            .map(|invite| invite.invite_link.clone())
+            .collect::<Vec<_>>();
+        // 招待の中からサーバーIDが取れたものを選ぶ
+        let invites = invites
+            .iter()
+            .filter_map(|invite| {
+                invite
+                    .guild_id
+                    .map(|guild_id| (invite.invite_code.clone(), guild_id))
+            })
+            .collect::<Vec<(String, GuildId)>>();
+
+        // スタッフ用チャンネルに承認待ちメッセージを送信
+        let locale = self.locale(review_channel, post.guild_id);
+        let args = [
+            ("user", post.author.mention().to_string()),
+            ("channel", post.channel_id.mention().to_string()),
+        ];
+        let review = review_channel
+            .send_message(ctx, |m| {
+                m.embed(|e| {
+                    e.title(self.text(locale, "approval.title", &[]));
+                    e.description(self.text(locale, "approval.description", &args));
+                    e.author(|a| a.name(post.author.tag()).icon_url(post.author.face()));
+                    if !msg.content.is_empty() {
+                        e.field(
+                            self.text(locale, "approval.content_name", &[]),
+                            truncate_field(&msg.content),
+                            false,
+                        );
+                    }
+                    if !links.is_empty() {
+                        e.field(
+                            self.text(locale, "approval.invites_name", &[]),
+                            truncate_field(&links.join("\n")),
+                            false,
+                        );
+                    }
+                    e.timestamp(post.timestamp)
+                });
+                m.add_files(attachments);
+                m.components(|c| {
+                    c.create_action_row(|row| {
+                        row.create_button(|b| {
+                            b.style(ButtonStyle::Success)
+                                .label(self.text(locale, "approval.approve", &[]))
+                                .custom_id(APPROVE_BUTTON_ID)
+                        })
+                        .create_button(|b| {
+                            b.style(ButtonStyle::Danger)
+                                .label(self.text(locale, "approval.reject", &[]))
+                                .custom_id(REJECT_BUTTON_ID)
+                        })
+                    })
+                })
+            })
+            .await
+            .context("承認待ちメッセージの送信に失敗")?;
+
+        // 承認待ちに登録
+        self.history
+            .insert_approval(&ApprovalRecord {
+                review_message_id: review.id,
+                guild_id: post.guild_id,
+                channel_id: post.channel_id,
+                user_id: post.author.id,
+                content: msg.content.clone(),
+                invites,
+                fingerprint,
+                timestamp: post.timestamp.unix_timestamp(),
+            })
+            .await
+            .context("承認待ちの登録に失敗")?;
+
+        // 元の投稿を削除
+        msg.delete(ctx)
+            .await
+            .context("承認待ちの投稿の削除に失敗")?;
+
+        // 投稿者に通知 (DMを受け付けていない場合は諦める)
+        let locale = self.locale(post.channel_id, post.guild_id);
+        let notice = self.text(
+            locale,
+            "approval.queued",
+            &[("channel", post.channel_id.mention().to_string())],
+        );
+        if let Err(why) = post.author.direct_message(ctx, |m| m.content(notice)).await {
+            warn!("承認待ちの通知に失敗: {:?}", why);
+        }
+
+        Ok(())
+    }
+
+    /// 承認待ちの宣伝を承認または却下する
+    pub(super) async fn review_promotion(
+        &self,
+        ctx: &Context,
+        component: &MessageComponentInteraction,
+        approve: bool,
+    ) -> Result<()> {
+        let locale = self.locale(component.channel_id, component.guild_id);
+
+        // メッセージの管理権限を持つスタッフのみ操作できる
+        let permitted = component
+            .member
+            .as_ref()
+            .and_then(|member| member.permissions)
+            .is_some_and(|permissions| permissions.manage_messages());
+        if !permitted {
+            let content = self.text(locale, "approval.no_permission", &[]);
+            return self.reply_ephemeral(ctx, component, content).await;
+        }
+
+        // 承認待ちから取り出す (同時に押された場合は先に取り出した方のみ処理する)
+        let record = match self.history.get_approval(&component.message.id).await? {
+            Some(record) => record,
+            None => {
+                let content = self.text(locale, "approval.not_found", &[]);
+                return self.reply_ephemeral(ctx, component, content).await;
+            }
+        };
+        if !self
+            .history
+            .delete_approval(&record.review_message_id)
+            .await?
+        {
+            let content = self.text(locale, "approval.not_found", &[]);
+            return self.reply_ephemeral(ctx, component, content).await;
+        }
+
+        let result = if approve {
+            // 投稿者の名前で再投稿し、履歴に登録する (失敗した場合は承認待ちに戻す)
+            match self
+                .publish_approved(ctx, &record, &component.message.attachments)
+                .await
+            {
+                Ok(None) => "approval.approved",
+                // 承認を待つ間に同じサーバーが宣伝された場合は投稿せず、投稿者に警告を送る
+                Ok(Some(alert)) => {
+                    let dm = match record.user_id.create_dm_channel(ctx).await {
+                        Ok(channel) => channel
+                            .send_message(ctx, |m| m.content(alert.content).set_embed(alert.embed))
+                            .await
+                            .map(|_| ()),
+                        Err(why) => Err(why),
+                    };
+                    if let Err(why) = dm {
+                        warn!("承認されなかった宣伝の警告の送信に失敗: {:?}", why);
+                    }
+                    "approval.blocked"
+                }
+                Err(why) => {
+                    self.history.insert_approval(&record).await?;
+                    return Err(why);
+                }
+            }
+        } else {
+            // 投稿者に通知 (DMを受け付けていない場合は諦める)
+            let post_locale = self.locale(record.channel_id, record.guild_id);
+            let notice = self.text(
+                post_locale,
+                "approval.rejected_notice",
+                &[("channel", record.channel_id.mention().to_string())],
+            );
+            let dm = match record.user_id.create_dm_channel(ctx).await {
+                Ok(channel) => channel.say(ctx, notice).await.map(|_| ()),
+                Err(why) => Err(why),
+            };
+            if let Err(why) = dm {
+                warn!("却下の通知に失敗: {:?}", why);
+            }
+            "approval.rejected"
+        };
+
+        // 結果を追記し、ボタンを取り除く
+        let mut embed = component
+            .message
+            .embeds
+            .first()
+            .cloned()
+            .map(CreateEmbed::from)
+            .unwrap_or_default();
+        embed.field(
+            self.text(locale, "approval.result_name", &[]),
+            self.text(
+                locale,
+                result,
+                &[("moderator", component.user.mention().to_string())],
+            ),
+            false,
+        );
+        component
+            .create_interaction_response(&ctx.http, |response| {
+                response
+                    .kind(InteractionResponseType::UpdateMessage)
+                    .interaction_response_data(|data| data.set_embed(embed).components(|c| c))
+            })
+            .await
+            .context("承認待ちメッセージの更新に失敗")?;
+
+        Ok(())
+    }
+
+    /// 承認された宣伝を投稿者の名前で投稿し、履歴に登録する (クールダウン中で投稿できない場合は警告を返す)
+    async fn publish_approved(
+        &self,
+        ctx: &Context,
+        record: &ApprovalRecord,
+        attachments: &[Attachment],
+    ) -> Result<Option<Alert>> {
+        let author = record
+            .user_id
+            .to_user(ctx)
+            .await
+            .context("投稿者の取得に失敗")?;
+
+        // 承認を待つ間に同じサーバーが宣伝されていないか、改めて検証する
+        let post = Post {
+            id: record.review_message_id,
+            channel_id: record.channel_id,
+            thread_id: None,
+            guild_id: record.guild_id,
+            author: author.clone(),
+            joined_at: None,
+            content: record.content.clone(),
+            title: None,
+            timestamp: Timestamp::now(),
+            mention_everyone: false,
+            mention_roles: vec![],
+            mention_users: 0,
+        };
+        let invites = record
+            .invites
+            .iter()
+            .map(|(code, invite_guild_id)| DiscordInviteLink {
+                invite_code: code.clone(),
+                guild_id: Some(*invite_guild_id),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        if let Some(alert) = self.check_cooldown(ctx, &post, &invites).await? {
+            return Ok(Some(alert));
+        }
+
+        let nick = match record.guild_id {
+            Some(guild_id) => guild_id
+                .member(ctx, record.user_id)
+                .await
+                .ok()
+                .and_then(|member| member.nick),
+            None => None,
+        };
+        let repost = Repost {
+            display_name: nick.unwrap_or_else(|| author.name.clone()),
+            author: &author,
+            content: record.content.clone(),
+            // 承認待ちメッセージに添付し直したファイルを使う
+            attachments: attachments
+                .iter()
+                .map(|attachment| attachment.url.clone())
+                .collect(),
+        };
+        let posted = self
+            .repost_as_author(ctx, record.channel_id, &repost)
+            .await
+            .context("承認された宣伝の投稿に失敗")?;
+
+        // クールダウンは投稿された時点から数える
+        let timestamp = Utc::now().timestamp();
+        let invite_result = record.invites.iter().map(|(code, invite_guild_id)| {
+            self.history.insert(HistoryRecord {
+                invite_code: code.clone(),
+                invite_guild_id: *invite_guild_id,
+                guild_id: record.guild_id,
+                channel_id: record.channel_id,
+                thread_id: None,
+                message_id: posted.id,
+                user_id: record.user_id,
+                timestamp,
+                deleted: false,
+            })
+        });
+        try_join_all(invite_result)
+            .await
+            .context("履歴の登録に失敗")?;
+        // 説明文を履歴に登録
+        self.history
+            .insert_description(DescriptionRecord {
+                guild_id: record.guild_id,
+                channel_id: record.channel_id,
+                message_id: posted.id,
+                user_id: record.user_id,
+                fingerprint: record.fingerprint.clone(),
+                timestamp,
+            })
+            .await
+            .context("説明文の履歴の登録に失敗")?;
+
+        Ok(None)
+    }
+}
@@ -0,0 +1,381 @@
+use anyhow::{Context as _, Result};
+use chrono::{DateTime, Duration, Utc};
+use log::warn;
+use serenity::builder::CreateComponents;
+use serenity::model::{
+    channel::{Message, PermissionOverwriteType},
+    id::{ChannelId, GuildId, MessageId, UserId},
+    interactions::{
+        message_component::{ButtonStyle, MessageComponentInteraction},
+        InteractionResponseType,
+    },
+};
+use serenity::prelude::*;
+
+use super::approval::{APPROVE_BUTTON_ID, REJECT_BUTTON_ID};
+use super::{Handler, Post};
+use crate::description;
+use crate::invite_finder::InviteFinder;
+
+/// 本文をDMで送るボタンのカスタムID
+const COPY_BUTTON_ID: &str = "warning_copy";
+/// 次に宣伝できる日時を表示するボタンのカスタムID
+const DUE_BUTTON_ID: &str = "warning_due";
+/// 宣伝できるようになった時に通知を受け取るボタンのカスタムID
+const REMIND_BUTTON_ID: &str = "warning_remind";
+/// モデレーターが投稿を許可するボタンのカスタムID
+const OVERRIDE_BUTTON_ID: &str = "warning_override";
+/// DMの警告のボタンを使える日数 (次に宣伝できる日時がある場合はその日時から数える)
+const WARNING_RETENTION_DAYS: i64 = 1;
+
+/// ボタンを表示している警告
+#[derive(Debug, Clone)]
+pub(super) struct Warning {
+    /// ギルドID
+    pub guild_id: Option<GuildId>,
+    /// 規制対象のチャンネルID
+    pub channel_id: ChannelId,
+    /// 投稿されたスレッドID
+    pub thread_id: Option<ChannelId>,
+    /// 警告された投稿のメッセージID
+    pub message_id: MessageId,
+    /// 投稿者のID
+    pub user_id: UserId,
+    /// 投稿の本文
+    pub content: String,
+    /// 次に宣伝できる日時
+    pub due: Option<DateTime<Utc>>,
+    /// 待てば宣伝できるサーバーのギルドIDと招待コード
+    pub due_invite: Option<(GuildId, String)>,
+    /// モデレーターが投稿を許可したか
+    pub overridden: bool,
+    /// ボタンを使えなくなる日時
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Warning {
+    /// 警告された投稿から作成する
+    pub fn new(
+        msg: &Message,
+        post: &Post,
+        due: Option<DateTime<Utc>>,
+        due_invite: Option<(GuildId, String)>,
+    ) -> Warning {
+        Warning {
+            guild_id: post.guild_id,
+            channel_id: post.channel_id,
+            thread_id: post.thread_id,
+            message_id: msg.id,
+            user_id: msg.author.id,
+            content: msg.content.clone(),
+            due,
+            due_invite,
+            overridden: false,
+            expires_at: due.unwrap_or_else(Utc::now).max(Utc::now())
+                + Duration::days(WARNING_RETENTION_DAYS),
+        }
+    }
+}
+
+impl Handler {
+    /// 警告メッセージに付けるボタンを作成する (投稿者向け、モデレーター向けを選ぶ)
+    pub(super) fn warning_buttons(
+        &self,
+        locale: &str,
+        has_due: bool,
+        for_author: bool,
+        for_moderator: bool,
+    ) -> CreateComponents {
+        let mut components = CreateComponents::default();
+        components.create_action_row(|row| {
+            if for_author {
+                row.create_button(|b| {
+                    b.style(ButtonStyle::Secondary)
+                        .label(self.text(locale, "warning.copy", &[]))
+                        .custom_id(COPY_BUTTON_ID)
+                });
+            }
+            // 待てば宣伝できる警告の場合のみ
+            if for_author && has_due {
+                row.create_button(|b| {
+                    b.style(ButtonStyle::Secondary)
+                        .label(self.text(locale, "warning.due", &[]))
+                        .custom_id(DUE_BUTTON_ID)
+                });
+                row.create_button(|b| {
+                    b.style(ButtonStyle::Secondary)
+                        .label(self.text(locale, "warning.remind", &[]))
+                        .custom_id(REMIND_BUTTON_ID)
+                });
+            }
+            if for_moderator {
+                row.create_button(|b| {
+                    b.style(ButtonStyle::Danger)
+                        .label(self.text(locale, "warning.override", &[]))
+                        .custom_id(OVERRIDE_BUTTON_ID)
+                });
+            }
+            row
+        });
+        components
+    }
+
+    /// メッセージのボタンが押された時の処理
+    pub(super) async fn on_message_component(
+        &self,
+        ctx: &Context,
+        component: &MessageComponentInteraction,
+    ) -> Result<()> {
+        match component.data.custom_id.as_str() {
+            APPROVE_BUTTON_ID => self
+                .review_promotion(ctx, component, true)
+                .await
+                .context("宣伝の承認に失敗"),
+            REJECT_BUTTON_ID => self
+                .review_promotion(ctx, component, false)
+                .await
+                .context("宣伝の却下に失敗"),
+            COPY_BUTTON_ID => self
+                .send_warning_copy(ctx, component)
+                .await
+                .context("投稿の本文の送信に失敗"),
+            DUE_BUTTON_ID => self
+                .show_warning_due(ctx, component)
+                .await
+                .context("次に宣伝できる日時の表示に失敗"),
+            REMIND_BUTTON_ID => self
+                .remind_warning_due(ctx, component)
+                .await
+                .context("リマインダーの登録に失敗"),
+            OVERRIDE_BUTTON_ID => self
+                .override_warning(ctx, component)
+                .await
+                .context("投稿の許可に失敗"),
+            _ => Ok(()), // 不明なボタン
+        }
+    }
+
+    /// ボタンを押したユーザーにのみ返信する
+    pub(super) async fn reply_ephemeral(
+        &self,
+        ctx: &Context,
+        component: &MessageComponentInteraction,
+        content: String,
+    ) -> Result<()> {
+        component
+            .create_interaction_response(&ctx.http, |response| {
+                response
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|data| data.content(content).ephemeral(true))
+            })
+            .await
+            .context("ボタンの返信に失敗")
+    }
+
+    /// ボタンが押された警告を取得する (期限切れの場合は返信してNone)
+    async fn find_warning(
+        &self,
+        ctx: &Context,
+        component: &MessageComponentInteraction,
+    ) -> Result<Option<Warning>> {
+        let warning = self
+            .warnings
+            .lock()
+            .await
+            .get(&component.message.id)
+            .filter(|warning| !warning.overridden && warning.expires_at > Utc::now())
+            .cloned();
+        if warning.is_none() {
+            let locale = self.locale(component.channel_id, component.guild_id);
+            let content = self.text(locale, "warning.expired", &[]);
+            self.reply_ephemeral(ctx, component, content).await?;
+        }
+        Ok(warning)
+    }
+
+    /// 警告された投稿の本文を投稿者にDMで送る
+    async fn send_warning_copy(
+        &self,
+        ctx: &Context,
+        component: &MessageComponentInteraction,
+    ) -> Result<()> {
+        let warning = match self.find_warning(ctx, component).await? {
+            Some(warning) => warning,
+            None => return Ok(()),
+        };
+        let locale = self.locale(warning.channel_id, warning.guild_id);
+
+        // 投稿者のみ操作できる
+        if component.user.id != warning.user_id {
+            let content = self.text(locale, "warning.not_author", &[]);
+            return self.reply_ephemeral(ctx, component, content).await;
+        }
+
+        // DMを受け付けていない場合は案内する
+        let key = match component
+            .user
+            .direct_message(ctx, |m| m.content(&warning.content))
+            .await
+        {
+            Ok(_) => "warning.copy_sent",
+            Err(why) => {
+                warn!("投稿の本文のDM送信に失敗: {:?}", why);
+                "warning.copy_failed"
+            }
+        };
+        let content = self.text(locale, key, &[]);
+        self.reply_ephemeral(ctx, component, content).await
+    }
+
+    /// 次に宣伝できる日時を投稿者に表示する
+    async fn show_warning_due(
+        &self,
+        ctx: &Context,
+        component: &MessageComponentInteraction,
+    ) -> Result<()> {
+        let warning = match self.find_warning(ctx, component).await? {
+            Some(warning) => warning,
+            None => return Ok(()),
+        };
+        let locale = self.locale(warning.channel_id, warning.guild_id);
+
+        let content = match warning.due {
+            Some(due) => self.text(
+                locale,
+                "warning.due_value",
+                &[("date", self.format_date(locale, &due))],
+            ),
+            None => self.text(locale, "warning.no_due", &[]),
+        };
+        self.reply_ephemeral(ctx, component, content).await
+    }
+
+    /// 宣伝できるようになった時にDMで通知するよう登録する
+    async fn remind_warning_due(
+        &self,
+        ctx: &Context,
+        component: &MessageComponentInteraction,
+    ) -> Result<()> {
+        let warning = match self.find_warning(ctx, component).await? {
+            Some(warning) => warning,
+            None => return Ok(()),
+        };
+        let locale = self.locale(warning.channel_id, warning.guild_id);
+
+        // 投稿者のみ操作できる
+        if component.user.id != warning.user_id {
+            let content = self.text(locale, "warning.not_author", &[]);
+            return self.reply_ephemeral(ctx, component, content).await;
+        }
+
+        let content = match warning.due {
+            Some(due) => {
+                self.schedule_reminder(
+                    warning.user_id,
+                    warning.guild_id,
+                    warning.channel_id,
+                    warning.due_invite,
+                    due,
+                )
+                .await?;
+                self.text(
+                    locale,
+                    "reminder.scheduled",
+                    &[("date", self.format_date(locale, &due))],
+                )
+            }
+            None => self.text(locale, "warning.no_due", &[]),
+        };
+        self.reply_ephemeral(ctx, component, content).await
+    }
+
+    /// モデレーターが警告を取り消して投稿を許可する
+    async fn override_warning(
+        &self,
+        ctx: &Context,
+        component: &MessageComponentInteraction,
+    ) -> Result<()> {
+        let locale = self.locale(component.channel_id, component.guild_id);
+
+        // メッセージの管理権限を持つモデレーターのみ操作できる
+        let permitted = component
+            .member
+            .as_ref()
+            .and_then(|member| member.permissions)
+            .is_some_and(|permissions| permissions.manage_messages());
+        if !permitted {
+            let content = self.text(locale, "warning.no_permission", &[]);
+            return self.reply_ephemeral(ctx, component, content).await;
+        }
+
+        // 許可済みにして、一定時間後に投稿が削除されないようにする
+        let warning = {
+            let mut warnings = self.warnings.lock().await;
+            match warnings.get_mut(&component.message.id) {
+                Some(warning) if !warning.overridden => {
+                    warning.overridden = true;
+                    Some(warning.clone())
+                }
+                _ => None,
+            }
+        };
+        let warning = match warning {
+            Some(warning) => warning,
+            None => {
+                let content = self.text(locale, "warning.expired", &[]);
+                return self.reply_ephemeral(ctx, component, content).await;
+            }
+        };
+
+        let content = self.text(
+            locale,
+            "warning.overridden",
+            &[("moderator", component.user.mention().to_string())],
+        );
+        self.reply_ephemeral(ctx, component, content).await?;
+
+        // 誤った警告は違反回数に数えず、期限付きで剥奪した投稿権限はすぐに戻す
+        let restore = self
+            .history
+            .delete_violation(&warning.message_id)
+            .await
+            .context("違反の記録の取り消しに失敗")?;
+        if let Some(record) = restore {
+            record
+                .channel_id
+                .delete_permission(ctx, PermissionOverwriteType::Member(record.user_id))
+                .await
+                .context("投稿権限の復元に失敗")?;
+        }
+
+        // 警告メッセージを削除
+        component
+            .message
+            .delete(ctx)
+            .await
+            .context("警告メッセージの削除に失敗")?;
+
+        // 許可された投稿を履歴に登録
+        let channel_id = warning.thread_id.unwrap_or(warning.channel_id);
+        let msg = channel_id
+            .message(ctx, warning.message_id)
+            .await
+            .context("許可された投稿の取得に失敗")?;
+        let mut post = Post::from_message(&msg, warning.channel_id, None);
+        post.thread_id = warning.thread_id;
+        let mut finder = InviteFinder::from_message(&msg, None, None)
+            .await
+            .context("招待リンクの検索に失敗")?;
+        finder
+            .resolve_shortened_links(&self.app_config.shortener)
+            .await
+            .context("短縮URLの解決に失敗")?;
+        let invites = finder
+            .get_invite_list(&ctx.http)
+            .await
+            .context("招待リンク情報の取得に失敗")?;
+        let fingerprint = description::fingerprint(&finder.description());
+        self.record_history(&post, post.thread_id, post.id, &invites, fingerprint)
+            .await
+    }
+}
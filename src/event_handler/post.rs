@@ -0,0 +1,127 @@
+use anyhow::{Context as _, Result};
+use serenity::model::{
+    channel::{Channel, ChannelType, GuildChannel, Message},
+    id::{ChannelId, GuildId, MessageId, RoleId},
+    user::User,
+    Timestamp,
+};
+use serenity::prelude::*;
+
+use super::Handler;
+
+/// 検証する宣伝の投稿
+pub(super) struct Post {
+    /// メッセージID
+    pub id: MessageId,
+    /// 規制対象のチャンネルID (スレッド内の投稿の場合は親チャンネル)
+    pub channel_id: ChannelId,
+    /// 投稿されたスレッドID
+    pub thread_id: Option<ChannelId>,
+    /// ギルドID
+    pub guild_id: Option<GuildId>,
+    /// 投稿者
+    pub author: User,
+    /// 投稿者がサーバーに参加した日時 (不明な場合はNone)
+    pub joined_at: Option<Timestamp>,
+    /// 本文
+    pub content: String,
+    /// フォーラムの投稿タイトル (フォーラムの最初のメッセージの場合のみ)
+    pub title: Option<String>,
+    /// 投稿日時
+    pub timestamp: Timestamp,
+    /// @everyone, @here で通知したか
+    pub mention_everyone: bool,
+    /// メンションしたロール
+    pub mention_roles: Vec<RoleId>,
+    /// メンションしたユーザーの数
+    pub mention_users: usize,
+}
+
+impl Post {
+    /// メッセージから投稿を作成する
+    pub fn from_message(
+        msg: &Message,
+        channel_id: ChannelId,
+        thread: Option<&GuildChannel>,
+    ) -> Post {
+        Post {
+            id: msg.id,
+            channel_id,
+            thread_id: thread.map(|thread| thread.id),
+            guild_id: msg.guild_id,
+            author: msg.author.clone(),
+            joined_at: msg.member.as_ref().and_then(|member| member.joined_at),
+            content: msg.content.clone(),
+            // フォーラムの投稿はスレッドIDと最初のメッセージIDが一致する
+            title: thread
+                .filter(|thread| thread.id.0 == msg.id.0)
+                .map(|thread| thread.name.clone()),
+            timestamp: msg.timestamp,
+            mention_everyone: msg.mention_everyone,
+            mention_roles: msg.mention_roles.clone(),
+            mention_users: msg.mentions.len(),
+        }
+    }
+
+    /// メッセージが投稿されたチャンネルID (スレッド内の投稿の場合はスレッドID)
+    pub fn message_channel_id(&self) -> ChannelId {
+        self.thread_id.unwrap_or(self.channel_id)
+    }
+}
+
+impl Handler {
+    /// 規制対象のチャンネルを取得する (スレッドの場合は親チャンネルとスレッド、規制対象外の場合はNone)
+    pub(super) async fn resolve_post_channel(
+        &self,
+        ctx: &Context,
+        channel_id: ChannelId,
+        guild_id: Option<GuildId>,
+    ) -> Result<Option<(ChannelId, Option<GuildChannel>)>> {
+        let channels = &self.app_config.discord.channels;
+        // 規制対象のチャンネル
+        if channels.contains(&channel_id) {
+            return Ok(Some((channel_id, None)));
+        }
+        let guild_id = match guild_id {
+            Some(guild_id) => guild_id,
+            None => return Ok(None),
+        };
+
+        // キャッシュからスレッドを探す
+        let cached_thread = ctx
+            .cache
+            .guild_field(guild_id, |guild| {
+                guild
+                    .threads
+                    .iter()
+                    .find(|thread| thread.id == channel_id)
+                    .cloned()
+            })
+            .flatten();
+        let thread = match cached_thread {
+            Some(thread) => thread,
+            // キャッシュにある通常のチャンネルはスレッドではない
+            None if ctx.cache.guild_channel(channel_id).is_some() => return Ok(None),
+            None => match channel_id
+                .to_channel(ctx)
+                .await
+                .context("チャンネル情報の取得に失敗")?
+            {
+                Channel::Guild(channel) => channel,
+                _ => return Ok(None),
+            },
+        };
+
+        // 親チャンネルが規制対象のスレッド (フォーラムの投稿を含む)
+        let is_thread = matches!(
+            thread.kind,
+            ChannelType::PublicThread | ChannelType::PrivateThread | ChannelType::NewsThread
+        );
+        match thread.parent_id {
+            Some(parent_id) if is_thread && channels.contains(&parent_id) => {
+                Ok(Some((parent_id, Some(thread))))
+            }
+            _ => Ok(None),
+        }
+    }
+}
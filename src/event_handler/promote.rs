@@ -0,0 +1,280 @@
+use anyhow::{Context as _, Result};
+use serenity::builder::CreateEmbed;
+use serenity::model::{
+    id::MessageId,
+    interactions::{
+        application_command::ApplicationCommandInteraction,
+        message_component::{ActionRowComponent, InputTextStyle},
+        modal::ModalSubmitInteraction,
+        InteractionResponseType,
+    },
+    Timestamp,
+};
+use serenity::prelude::*;
+
+use super::approval::truncate_field;
+use super::{Handler, Post, Verdict};
+use crate::invite_finder::{DiscordInviteLink, InviteFinder};
+
+/// 宣伝フォームのカスタムID
+const PROMOTE_MODAL_ID: &str = "promote";
+/// 招待リンク欄のカスタムID
+const INVITE_INPUT_ID: &str = "invite";
+/// 説明文欄のカスタムID
+const DESCRIPTION_INPUT_ID: &str = "description";
+/// タグ欄のカスタムID
+const TAGS_INPUT_ID: &str = "tags";
+
+/// 宣伝フォームに入力された内容
+struct PromoteForm {
+    /// 招待リンク
+    invite: String,
+    /// 説明文
+    description: String,
+    /// タグ
+    tags: Vec<String>,
+}
+
+impl PromoteForm {
+    /// フォームの送信内容から作成する
+    fn from_modal(modal: &ModalSubmitInteraction) -> PromoteForm {
+        let value = |custom_id: &str| {
+            modal
+                .data
+                .components
+                .iter()
+                .flat_map(|row| row.components.iter())
+                .find_map(|component| match component {
+                    ActionRowComponent::InputText(input) if input.custom_id == custom_id => {
+                        Some(input.value.trim().to_string())
+                    }
+                    _ => None,
+                })
+                .unwrap_or_default()
+        };
+        PromoteForm {
+            invite: value(INVITE_INPUT_ID),
+            description: value(DESCRIPTION_INPUT_ID),
+            // カンマ区切り (全角、読点を含む)
+            tags: value(TAGS_INPUT_ID)
+                .split([',', '，', '、'])
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+}
+
+impl Handler {
+    /// 宣伝フォームを表示する
+    pub(super) async fn command_promote(
+        &self,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+    ) -> Result<()> {
+        let locale = self.locale(command.channel_id, command.guild_id);
+        command
+            .create_interaction_response(&ctx.http, |response| {
+                response
+                    .kind(InteractionResponseType::Modal)
+                    .interaction_response_data(|data| {
+                        data.custom_id(PROMOTE_MODAL_ID)
+                            .title(self.text(locale, "promote.modal_title", &[]))
+                            .components(|c| {
+                                c.create_action_row(|row| {
+                                    row.create_input_text(|input| {
+                                        input
+                                            .custom_id(INVITE_INPUT_ID)
+                                            .style(InputTextStyle::Short)
+                                            .label(self.text(locale, "promote.invite_label", &[]))
+                                            .placeholder("https://discord.gg/...")
+                                            .max_length(100)
+                                            .required(true)
+                                    })
+                                })
+                                .create_action_row(|row| {
+                                    row.create_input_text(|input| {
+                                        input
+                                            .custom_id(DESCRIPTION_INPUT_ID)
+                                            .style(InputTextStyle::Paragraph)
+                                            .label(self.text(
+                                                locale,
+                                                "promote.description_label",
+                                                &[],
+                                            ))
+                                            .max_length(4000)
+                                            .required(true)
+                                    })
+                                })
+                                .create_action_row(|row| {
+                                    row.create_input_text(|input| {
+                                        input
+                                            .custom_id(TAGS_INPUT_ID)
+                                            .style(InputTextStyle::Short)
+                                            .label(self.text(locale, "promote.tags_label", &[]))
+                                            .placeholder(self.text(
+                                                locale,
+                                                "promote.tags_placeholder",
+                                                &[],
+                                            ))
+                                            .max_length(100)
+                                            .required(false)
+                                    })
+                                })
+                            })
+                    })
+            })
+            .await
+            .context("宣伝フォームの表示に失敗")
+    }
+
+    /// フォームが送信された時の処理
+    pub(super) async fn on_modal_submit(
+        &self,
+        ctx: &Context,
+        modal: &ModalSubmitInteraction,
+    ) -> Result<()> {
+        if modal.data.custom_id != PROMOTE_MODAL_ID {
+            return Ok(()); // 不明なフォーム
+        }
+
+        // 検証に時間がかかるため、先に応答しておく (結果は送信したユーザーにのみ表示する)
+        modal
+            .create_interaction_response(&ctx.http, |response| {
+                response
+                    .kind(InteractionResponseType::DeferredChannelMessageWithSource)
+                    .interaction_response_data(|data| data.ephemeral(true))
+            })
+            .await
+            .context("宣伝フォームの応答に失敗")?;
+
+        let form = PromoteForm::from_modal(modal);
+        let (content, embed) = self
+            .submit_promotion(ctx, modal, &form)
+            .await
+            .context("宣伝フォームの投稿に失敗")?;
+        modal
+            .edit_original_interaction_response(&ctx.http, |response| {
+                response.content(content);
+                if let Some(embed) = embed {
+                    response.set_embed(embed);
+                }
+                response
+            })
+            .await
+            .context("宣伝フォームの結果の返信に失敗")?;
+
+        Ok(())
+    }
+
+    /// フォームの内容を検証して投稿する (返信する本文と埋め込みを返す)
+    async fn submit_promotion(
+        &self,
+        ctx: &Context,
+        modal: &ModalSubmitInteraction,
+        form: &PromoteForm,
+    ) -> Result<(String, Option<CreateEmbed>)> {
+        // 規制対象のチャンネル (とその中のスレッド、フォーラムの投稿) でのみ投稿できる
+        let (channel_id, thread) = match self
+            .resolve_post_channel(ctx, modal.channel_id, modal.guild_id)
+            .await?
+        {
+            Some(channel) => channel,
+            None => {
+                let locale = self.locale(modal.channel_id, modal.guild_id);
+                return Ok((self.text(locale, "promote.not_channel", &[]), None));
+            }
+        };
+        let locale = self.locale(channel_id, modal.guild_id);
+
+        // 埋め込みで投稿するため、メンションは通知されない
+        let post = Post {
+            id: MessageId(modal.id.0),
+            channel_id,
+            thread_id: thread.map(|thread| thread.id),
+            guild_id: modal.guild_id,
+            author: modal.user.clone(),
+            joined_at: modal.member.as_ref().and_then(|member| member.joined_at),
+            content: form.description.clone(),
+            title: None,
+            timestamp: Timestamp::now(),
+            mention_everyone: false,
+            mention_roles: vec![],
+            mention_users: 0,
+        };
+
+        // 承認制のチャンネルではスタッフの確認を通す
+        if self.requires_approval(&post) {
+            return Ok((self.text(locale, "promote.approval_required", &[]), None));
+        }
+
+        // 招待リンク、説明文、タグをまとめて検証する
+        let mut finder = InviteFinder::new(&format!(
+            "{}\n{}\n{}",
+            form.invite,
+            form.description,
+            form.tags.join(" ")
+        ))
+        .context("招待リンクの検索に失敗")?;
+        finder
+            .resolve_shortened_links(&self.app_config.shortener)
+            .await
+            .context("短縮URLの解決に失敗")?;
+        let (invites, fingerprint) = match self.check_post(ctx, &post, &finder).await? {
+            // 入力した内容が失われないよう、警告と一緒に返す
+            Verdict::Alert(alert) => {
+                let mut embed = alert.embed;
+                embed.field(
+                    self.text(locale, "promote.submitted_name", &[]),
+                    truncate_field(&form.description),
+                    false,
+                );
+                return Ok((alert.content, Some(embed)));
+            }
+            Verdict::Pass {
+                invites,
+                fingerprint,
+            } => (invites, fingerprint),
+        };
+
+        // 統一した書式で投稿する (招待リンクは本文に置いてプレビューを表示する)
+        let links = invites
+            .iter()
+            .map(DiscordInviteLink::url)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let posted = post
+            .message_channel_id()
+            .send_message(ctx, |m| {
+                m.content(&links);
+                m.embed(|e| {
+                    e.author(|a| a.name(post.author.tag()).icon_url(post.author.face()));
+                    e.description(&form.description);
+                    e.field(self.text(locale, "promote.invite_name", &[]), &links, false);
+                    if !form.tags.is_empty() {
+                        e.field(
+                            self.text(locale, "promote.tags_name", &[]),
+                            form.tags
+                                .iter()
+                                .map(|tag| format!("`{}`", tag))
+                                .collect::<Vec<_>>()
+                                .join(" "),
+                            false,
+                        );
+                    }
+                    e.timestamp(post.timestamp)
+                });
+                m.allowed_mentions(|a| a.empty_parse())
+            })
+            .await
+            .context("宣伝の投稿に失敗")?;
+
+        // 履歴に登録
+        self.record_history(&post, post.thread_id, posted.id, &invites, fingerprint)
+            .await?;
+
+        let link = posted.id.link(posted.channel_id, post.guild_id);
+        Ok((self.text(locale, "promote.posted", &[("link", link)]), None))
+    }
+}
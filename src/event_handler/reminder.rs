@@ -0,0 +1,154 @@
+use anyhow::{anyhow, Context as _, Result};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use log::warn;
+use serenity::model::{
+    id::{ChannelId, GuildId, MessageId, UserId},
+    interactions::application_command::ApplicationCommandInteraction,
+};
+use serenity::prelude::*;
+
+use super::slash_command::get_string_option;
+use super::Handler;
+use crate::history_log::{HistoryFindKey, HistoryRecord, ReminderRecord};
+use crate::invite_finder::InviteFinder;
+
+impl Handler {
+    /// 履歴の宣伝から、再び宣伝できるようになるまでの日数を取得する
+    pub(super) fn cooldown_days(&self, record: &HistoryRecord, user_id: UserId) -> i64 {
+        if record.user_id == user_id {
+            self.app_config.ban_period.day_per_user
+        } else {
+            self.app_config.ban_period.day
+        }
+    }
+
+    /// 宣伝できるようになった時にDMで通知するよう登録する
+    pub(super) async fn schedule_reminder(
+        &self,
+        user_id: UserId,
+        guild_id: Option<GuildId>,
+        channel_id: ChannelId,
+        invite: Option<(GuildId, String)>,
+        due: DateTime<Utc>,
+    ) -> Result<()> {
+        let (invite_guild_id, invite_code) = invite.unzip();
+        self.history
+            .insert_reminder(&ReminderRecord {
+                user_id,
+                guild_id,
+                channel_id,
+                invite_guild_id,
+                invite_code,
+                due: due.timestamp(),
+            })
+            .await
+            .context("リマインダーの登録に失敗")
+    }
+
+    /// 招待リンクのサーバーを宣伝できるようになった時に通知するよう登録する
+    pub(super) async fn command_remind(
+        &self,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+    ) -> Result<String> {
+        let locale = self.locale(command.channel_id, command.guild_id);
+        let server = get_string_option(&command.data.options, "server")
+            .ok_or_else(|| anyhow!("サーバーが指定されていません"))?;
+
+        // 規制対象のチャンネル (とその中のスレッド) でのみ登録できる
+        let channel_id = match self
+            .resolve_post_channel(ctx, command.channel_id, command.guild_id)
+            .await?
+        {
+            Some((channel_id, _thread)) => channel_id,
+            None => return Ok(self.text(locale, "reminder.not_channel", &[])),
+        };
+        let locale = self.locale(channel_id, command.guild_id);
+
+        // 招待リンクから招待コードとサーバーを取得
+        let finder = InviteFinder::new(server)?;
+        let invites = finder
+            .get_invite_list(&ctx.http)
+            .await
+            .context("招待リンク情報の取得に失敗")?;
+        if invites.is_empty() {
+            return Ok(self.text(
+                locale,
+                "reminder.not_found",
+                &[("server", server.to_string())],
+            ));
+        }
+        let keys = invites.iter().flat_map(|invite| {
+            [
+                Some(HistoryFindKey::InviteCode(invite.invite_code.clone())),
+                invite.guild_id.map(HistoryFindKey::InviteGuildId),
+            ]
+            .into_iter()
+            .flatten()
+        });
+
+        // 過去の宣伝から一番遠い期限を求める
+        let mut due = None;
+        for key in keys {
+            let records = self
+                .history
+                .validate(&MessageId(0), &channel_id, &command.user.id, &key)
+                .await?;
+            for record in records {
+                let days = self.cooldown_days(&record, command.user.id);
+                let record_due =
+                    NaiveDateTime::from_timestamp(record.timestamp, 0) + Duration::days(days);
+                due = due.max(Some(record_due));
+            }
+        }
+        let due = match due {
+            Some(due) => DateTime::<Utc>::from_utc(due, Utc),
+            None => return Ok(self.text(locale, "reminder.no_cooldown", &[])),
+        };
+
+        // 指定されたサーバーごとに登録する
+        let invite = invites.iter().find_map(|invite| {
+            invite
+                .guild_id
+                .map(|guild_id| (guild_id, invite.invite_code.clone()))
+        });
+        self.schedule_reminder(command.user.id, command.guild_id, channel_id, invite, due)
+            .await?;
+        Ok(self.text(
+            locale,
+            "reminder.scheduled",
+            &[("date", self.format_date(locale, &due))],
+        ))
+    }
+
+    /// 期限を過ぎたリマインダーをDMで送信する
+    pub(super) async fn send_due_reminders(&self, ctx: &Context) -> Result<()> {
+        let records = self
+            .history
+            .get_due_reminders(Utc::now().timestamp())
+            .await?;
+        for record in records {
+            let locale = self.locale(record.channel_id, record.guild_id);
+            let channel = record.channel_id.mention().to_string();
+            let notice = match &record.invite_code {
+                Some(invite_code) => self.text(
+                    locale,
+                    "reminder.due_server",
+                    &[("channel", channel), ("server", invite_code.clone())],
+                ),
+                None => self.text(locale, "reminder.due", &[("channel", channel)]),
+            };
+            // DMを受け付けていない場合は諦める
+            let dm = match record.user_id.create_dm_channel(ctx).await {
+                Ok(channel) => channel.say(ctx, notice).await.map(|_| ()),
+                Err(why) => Err(why),
+            };
+            if let Err(why) = dm {
+                warn!("リマインダーのDM送信に失敗: {:?}", why);
+            }
+            self.history.delete_reminder(&record).await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+use anyhow::{anyhow, Context as _, Result};
+use reqwest::Url;
+use serenity::model::{
+    channel::{AttachmentType, Message},
+    id::ChannelId,
+    user::User,
+    webhook::Webhook,
+};
+use serenity::prelude::*;
+
+use super::Handler;
+
+/// 再投稿に使用するWebhookの名前
+const WEBHOOK_NAME: &str = "discord-restricted-promotion";
+
+/// 再投稿する内容
+pub(super) struct Repost<'a> {
+    /// 投稿者
+    pub author: &'a User,
+    /// 表示名
+    pub display_name: String,
+    /// 本文
+    pub content: String,
+    /// 添付ファイルのURL
+    pub attachments: Vec<String>,
+}
+
+impl<'a> Repost<'a> {
+    /// メッセージから再投稿する内容を作成する
+    pub fn from_message(msg: &'a Message, content: String) -> Repost<'a> {
+        let display_name = msg
+            .member
+            .as_ref()
+            .and_then(|member| member.nick.clone())
+            .unwrap_or_else(|| msg.author.name.clone());
+        Repost {
+            author: &msg.author,
+            display_name,
+            content,
+            attachments: msg
+                .attachments
+                .iter()
+                .map(|attachment| attachment.url.clone())
+                .collect(),
+        }
+    }
+}
+
+impl Handler {
+    /// チャンネルのBot用Webhookを取得する (なければ作成する)
+    async fn get_webhook(&self, ctx: &Context, channel_id: ChannelId) -> Result<Webhook> {
+        let webhooks = channel_id
+            .webhooks(ctx)
+            .await
+            .context("Webhookの取得に失敗")?;
+        let webhook = webhooks.into_iter().find(|webhook| {
+            webhook.name.as_deref() == Some(WEBHOOK_NAME) && webhook.token.is_some()
+        });
+        match webhook {
+            Some(webhook) => Ok(webhook),
+            None => channel_id
+                .create_webhook(ctx, WEBHOOK_NAME)
+                .await
+                .context("Webhookの作成に失敗"),
+        }
+    }
+
+    /// 投稿者の名前とアイコンでチャンネルに再投稿する (メンションは通知しない)
+    pub(super) async fn repost_as_author(
+        &self,
+        ctx: &Context,
+        channel_id: ChannelId,
+        repost: &Repost<'_>,
+    ) -> Result<Message> {
+        let webhook = self.get_webhook(ctx, channel_id).await?;
+        let attachments = repost
+            .attachments
+            .iter()
+            .filter_map(|url| Url::parse(url).ok())
+            .map(AttachmentType::Image)
+            .collect::<Vec<_>>();
+
+        let message = webhook
+            .execute(ctx, true, |w| {
+                w.content(&repost.content)
+                    .username(&repost.display_name)
+                    .avatar_url(repost.author.face())
+                    .add_files(attachments)
+                    .allowed_mentions(|m| m.empty_parse())
+            })
+            .await
+            .context("Webhookでの再投稿に失敗")?
+            .ok_or_else(|| anyhow!("再投稿したメッセージが返されませんでした"))?;
+
+        Ok(message)
+    }
+}
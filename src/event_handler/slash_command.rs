@@ -0,0 +1,491 @@
+use anyhow::{anyhow, Context as _, Result};
+use chrono::Utc;
+use rand::seq::SliceRandom;
+use serenity::builder::{CreateApplicationCommand, CreateApplicationCommandOption};
+use serenity::model::{
+    id::GuildId,
+    interactions::{
+        application_command::{
+            ApplicationCommand, ApplicationCommandInteraction,
+            ApplicationCommandInteractionDataOption, ApplicationCommandInteractionDataOptionValue,
+            ApplicationCommandOptionType,
+        },
+        InteractionResponseType,
+    },
+    Permissions,
+};
+use serenity::prelude::*;
+use std::collections::HashMap;
+
+use super::Handler;
+use crate::history_log::{GuildListKind, GuildListRecord, OwnershipRecord};
+use crate::invite_finder::InviteFinder;
+
+/// 確認コードに使用する文字 (チャンネル名に使えて、見間違えにくい文字)
+const VERIFICATION_CODE_CHARS: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+/// 所属確認用のコードを生成する
+fn generate_verification_code() -> String {
+    let mut rng = rand::thread_rng();
+    let code = (0..8)
+        .map(|_| *VERIFICATION_CODE_CHARS.choose(&mut rng).unwrap_or(&b'a') as char)
+        .collect::<String>();
+    format!("verify-{}", code)
+}
+
+/// Discordが対応している言語
+const DISCORD_LOCALES: &[&str] = &[
+    "id", "da", "de", "en-GB", "en-US", "es-ES", "es-419", "fr", "hr", "it", "lt", "hu", "nl",
+    "no", "pl", "pt-BR", "ro", "fi", "sv-SE", "vi", "tr", "cs", "el", "bg", "ru", "uk", "hi", "th",
+    "zh-CN", "ja", "zh-TW", "ko",
+];
+
+/// 言語ごとのコマンドの説明文
+struct CommandDescription {
+    /// 既定の説明文
+    default: String,
+    /// Discordの言語 → 説明文
+    localizations: HashMap<&'static str, String>,
+}
+
+/// 言語ごとの説明文を設定できるビルダー
+trait LocalizedDescription {
+    /// 説明文を設定する
+    fn localized_description(&mut self, description: &CommandDescription) -> &mut Self;
+}
+
+impl LocalizedDescription for CreateApplicationCommand {
+    fn localized_description(&mut self, description: &CommandDescription) -> &mut Self {
+        // serenity 0.11.2 のビルダーには言語ごとの説明文を設定するメソッドがないため直接設定する
+        self.0.insert(
+            "description_localizations",
+            serde_json::json!(description.localizations),
+        );
+        self.description(&description.default)
+    }
+}
+
+impl LocalizedDescription for CreateApplicationCommandOption {
+    fn localized_description(&mut self, description: &CommandDescription) -> &mut Self {
+        self.0.insert(
+            "description_localizations",
+            serde_json::json!(description.localizations),
+        );
+        self.description(&description.default)
+    }
+}
+
+/// コマンドのオプションから文字列を取得する
+pub(super) fn get_string_option<'a>(
+    options: &'a [ApplicationCommandInteractionDataOption],
+    name: &str,
+) -> Option<&'a str> {
+    options
+        .iter()
+        .find(|option| option.name == name)
+        .and_then(|option| match &option.resolved {
+            Some(ApplicationCommandInteractionDataOptionValue::String(value)) => {
+                Some(value.as_str())
+            }
+            _ => None,
+        })
+}
+
+impl Handler {
+    /// リストの種類の表示名
+    fn kind_name(&self, locale: &str, kind: GuildListKind) -> String {
+        self.text(locale, &format!("server_list.{}", kind.as_str()), &[])
+    }
+
+    /// コマンドの説明文をメッセージカタログから取得する
+    fn command_description(&self, name: &str) -> CommandDescription {
+        let key = format!("command.{}", name);
+        // Discordの言語 (en-US など) → 地域を除いた言語 (en など) の順に探す
+        let localizations = DISCORD_LOCALES
+            .iter()
+            .filter_map(|discord_locale| {
+                let language = discord_locale.split('-').next().unwrap_or(discord_locale);
+                let text = self
+                    .messages
+                    .find(discord_locale, &key)
+                    .or_else(|| self.messages.find(language, &key))?;
+                Some((*discord_locale, text.to_string()))
+            })
+            .collect();
+        CommandDescription {
+            default: self.text(&self.app_config.message.locale, &key, &[]),
+            localizations,
+        }
+    }
+
+    /// スラッシュコマンドを登録する
+    pub(super) async fn register_commands(&self, ctx: &Context) -> Result<()> {
+        let describe = |name: &str| self.command_description(name);
+        ApplicationCommand::set_global_application_commands(&ctx.http, |commands| {
+            commands.create_application_command(|command| {
+                command
+                    .name("serverlist")
+                    .localized_description(&describe("serverlist"))
+                    .default_member_permissions(Permissions::MANAGE_GUILD)
+                    .dm_permission(false)
+                    .create_option(|option| {
+                        option
+                            .name("allow")
+                            .localized_description(&describe("serverlist_allow"))
+                            .kind(ApplicationCommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("server")
+                                    .localized_description(&describe("serverlist_server"))
+                                    .kind(ApplicationCommandOptionType::String)
+                                    .required(true)
+                            })
+                            .create_sub_option(|option| {
+                                option
+                                    .name("reason")
+                                    .localized_description(&describe("serverlist_reason"))
+                                    .kind(ApplicationCommandOptionType::String)
+                            })
+                    })
+                    .create_option(|option| {
+                        option
+                            .name("deny")
+                            .localized_description(&describe("serverlist_deny"))
+                            .kind(ApplicationCommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("server")
+                                    .localized_description(&describe("serverlist_server"))
+                                    .kind(ApplicationCommandOptionType::String)
+                                    .required(true)
+                            })
+                            .create_sub_option(|option| {
+                                option
+                                    .name("reason")
+                                    .localized_description(&describe("serverlist_reason"))
+                                    .kind(ApplicationCommandOptionType::String)
+                            })
+                    })
+                    .create_option(|option| {
+                        option
+                            .name("remove")
+                            .localized_description(&describe("serverlist_remove"))
+                            .kind(ApplicationCommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("server")
+                                    .localized_description(&describe("serverlist_server"))
+                                    .kind(ApplicationCommandOptionType::String)
+                                    .required(true)
+                            })
+                    })
+                    .create_option(|option| {
+                        option
+                            .name("show")
+                            .localized_description(&describe("serverlist_show"))
+                            .kind(ApplicationCommandOptionType::SubCommand)
+                    })
+            });
+            commands.create_application_command(|command| {
+                command
+                    .name("verify")
+                    .localized_description(&describe("verify"))
+                    .dm_permission(false)
+                    .create_option(|option| {
+                        option
+                            .name("server")
+                            .localized_description(&describe("server"))
+                            .kind(ApplicationCommandOptionType::String)
+                            .required(true)
+                    })
+            });
+            commands.create_application_command(|command| {
+                command
+                    .name("remind")
+                    .localized_description(&describe("remind"))
+                    .dm_permission(false)
+                    .create_option(|option| {
+                        option
+                            .name("server")
+                            .localized_description(&describe("server"))
+                            .kind(ApplicationCommandOptionType::String)
+                            .required(true)
+                    })
+            });
+            commands.create_application_command(|command| {
+                command
+                    .name("promote")
+                    .localized_description(&describe("promote"))
+                    .dm_permission(false)
+            })
+        })
+        .await
+        .context("スラッシュコマンドの登録に失敗")?;
+
+        Ok(())
+    }
+
+    /// スラッシュコマンドが実行された時の処理
+    pub(super) async fn on_application_command(
+        &self,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+    ) -> Result<()> {
+        // フォームを表示するコマンドは返信の代わりにフォームで応答する
+        if command.data.name == "promote" {
+            return self
+                .command_promote(ctx, command)
+                .await
+                .context("宣伝コマンドの実行に失敗");
+        }
+
+        // コマンドを実行
+        let content = match command.data.name.as_str() {
+            "serverlist" => self
+                .command_server_list(ctx, command)
+                .await
+                .context("許可/禁止リストコマンドの実行に失敗")?,
+            "verify" => self
+                .command_verify(ctx, command)
+                .await
+                .context("所属確認コマンドの実行に失敗")?,
+            "remind" => self
+                .command_remind(ctx, command)
+                .await
+                .context("リマインダーコマンドの実行に失敗")?,
+            _ => return Ok(()), // 不明なコマンド
+        };
+
+        // 実行したユーザーにのみ結果を返信
+        command
+            .create_interaction_response(&ctx.http, |response| {
+                response
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|data| data.content(content).ephemeral(true))
+            })
+            .await
+            .context("コマンドの返信に失敗")?;
+
+        Ok(())
+    }
+
+    /// サーバーIDまたは招待リンクからギルドIDを取得する
+    async fn resolve_guild_id(&self, ctx: &Context, server: &str) -> Result<Option<GuildId>> {
+        // サーバーIDが指定された
+        if let Ok(guild_id) = server.trim().parse::<u64>() {
+            return Ok(Some(GuildId(guild_id)));
+        }
+
+        // 招待リンクからギルドIDを取得
+        let finder = InviteFinder::new(server)?;
+        let invites = finder
+            .get_invite_list(&ctx.http)
+            .await
+            .context("招待リンク情報の取得に失敗")?;
+        Ok(invites.into_iter().find_map(|invite| invite.guild_id))
+    }
+
+    /// 許可/禁止リストを管理するコマンド
+    async fn command_server_list(
+        &self,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+    ) -> Result<String> {
+        let guild_id = command
+            .guild_id
+            .ok_or_else(|| anyhow!("サーバー外でコマンドが実行されました"))?;
+        let locale = self.locale(command.channel_id, command.guild_id);
+        let subcommand = command
+            .data
+            .options
+            .first()
+            .ok_or_else(|| anyhow!("サブコマンドが指定されていません"))?;
+
+        match subcommand.name.as_str() {
+            "allow" | "deny" => {
+                let kind = if subcommand.name == "allow" {
+                    GuildListKind::Allow
+                } else {
+                    GuildListKind::Deny
+                };
+                let server = get_string_option(&subcommand.options, "server")
+                    .ok_or_else(|| anyhow!("サーバーが指定されていません"))?;
+                let reason = get_string_option(&subcommand.options, "reason");
+
+                // 招待先のギルドIDを取得
+                let invite_guild_id = match self.resolve_guild_id(ctx, server).await? {
+                    Some(invite_guild_id) => invite_guild_id,
+                    None => {
+                        return Ok(self.text(
+                            locale,
+                            "server_list.not_found",
+                            &[("server", server.to_string())],
+                        ))
+                    }
+                };
+
+                // リストに登録
+                self.history
+                    .insert_guild_list(GuildListRecord {
+                        guild_id,
+                        invite_guild_id,
+                        kind,
+                        reason: reason.map(|reason| reason.to_string()),
+                        user_id: command.user.id,
+                        timestamp: Utc::now().timestamp(),
+                    })
+                    .await?;
+
+                Ok(self.text(
+                    locale,
+                    "server_list.added",
+                    &[
+                        ("guild", invite_guild_id.to_string()),
+                        ("kind", self.kind_name(locale, kind)),
+                    ],
+                ))
+            }
+            "remove" => {
+                let server = get_string_option(&subcommand.options, "server")
+                    .ok_or_else(|| anyhow!("サーバーが指定されていません"))?;
+
+                // 招待先のギルドIDを取得
+                let invite_guild_id = match self.resolve_guild_id(ctx, server).await? {
+                    Some(invite_guild_id) => invite_guild_id,
+                    None => {
+                        return Ok(self.text(
+                            locale,
+                            "server_list.not_found",
+                            &[("server", server.to_string())],
+                        ))
+                    }
+                };
+
+                // リストから削除
+                let key = if self
+                    .history
+                    .delete_guild_list(&guild_id, &invite_guild_id)
+                    .await?
+                {
+                    "server_list.removed"
+                } else {
+                    "server_list.not_registered"
+                };
+                Ok(self.text(locale, key, &[("guild", invite_guild_id.to_string())]))
+            }
+            "show" => {
+                let records = self.history.get_guild_lists(&guild_id).await?;
+                if records.is_empty() {
+                    return Ok(self.text(locale, "server_list.empty", &[]));
+                }
+
+                Ok(records
+                    .iter()
+                    .map(|record| {
+                        let reason = match &record.reason {
+                            Some(reason) => reason.clone(),
+                            None => self.text(locale, "common.no_reason", &[]),
+                        };
+                        self.text(
+                            locale,
+                            "server_list.entry",
+                            &[
+                                ("kind", self.kind_name(locale, record.kind)),
+                                ("guild", record.invite_guild_id.to_string()),
+                                ("reason", reason),
+                            ],
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+            }
+            _ => Err(anyhow!("不明なサブコマンド: {}", subcommand.name)),
+        }
+    }
+
+    /// 宣伝するサーバーに所属していることを確認するコマンド
+    async fn command_verify(
+        &self,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+    ) -> Result<String> {
+        let guild_id = command
+            .guild_id
+            .ok_or_else(|| anyhow!("サーバー外でコマンドが実行されました"))?;
+        let locale = self.locale(command.channel_id, command.guild_id);
+        let server = get_string_option(&command.data.options, "server")
+            .ok_or_else(|| anyhow!("サーバーが指定されていません"))?;
+
+        // 招待リンクから説明文とチャンネル名を取得
+        let finder = InviteFinder::new(server)?;
+        let invites = finder
+            .get_invite_list(&ctx.http)
+            .await
+            .context("招待リンク情報の取得に失敗")?;
+        let (invite, invite_guild_id) = match invites
+            .iter()
+            .find_map(|invite| Some((invite, invite.guild_id?)))
+        {
+            Some(invite) => invite,
+            None => {
+                return Ok(self.text(
+                    locale,
+                    "verify.not_found",
+                    &[("server", server.to_string())],
+                ))
+            }
+        };
+        let guild = invite_guild_id.to_string();
+
+        // 確認の状態を取得
+        let record = self
+            .history
+            .get_ownership(&Some(guild_id), &invite_guild_id, &command.user.id)
+            .await?;
+        let code = match record {
+            // 確認済み
+            Some(record) if record.verified => {
+                return Ok(self.text(locale, "verify.already_verified", &[("guild", guild)]));
+            }
+            // 確認コードが説明文かチャンネル名に含まれていれば確認済みにする
+            Some(record)
+                if [&invite.guild_description, &invite.channel_name]
+                    .iter()
+                    .filter_map(|text| text.as_ref())
+                    .any(|text| text.to_lowercase().contains(&record.code)) =>
+            {
+                self.history
+                    .insert_ownership(OwnershipRecord {
+                        verified: true,
+                        timestamp: Utc::now().timestamp(),
+                        ..record
+                    })
+                    .await?;
+                return Ok(self.text(locale, "verify.verified", &[("guild", guild)]));
+            }
+            // 確認コードを発行済み
+            Some(record) => record.code,
+            // 確認コードを発行する
+            None => {
+                let code = generate_verification_code();
+                self.history
+                    .insert_ownership(OwnershipRecord {
+                        guild_id,
+                        invite_guild_id,
+                        user_id: command.user.id,
+                        code: code.clone(),
+                        verified: false,
+                        timestamp: Utc::now().timestamp(),
+                    })
+                    .await?;
+                code
+            }
+        };
+
+        Ok(self.text(
+            locale,
+            "verify.instructions",
+            &[("guild", guild), ("code", code)],
+        ))
+    }
+}
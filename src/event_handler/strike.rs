@@ -0,0 +1,196 @@
+use anyhow::{anyhow, Context as _, Result};
+use chrono::{Duration, Utc};
+use log::error;
+use serenity::model::{
+    channel::{PermissionOverwrite, PermissionOverwriteType},
+    Permissions, Timestamp,
+};
+use serenity::prelude::*;
+
+use super::{Alert, Handler, Post};
+use crate::app_config::StrikeAction;
+use crate::history_log::ViolationRecord;
+
+/// タイムアウトできる最大の分数 (28日)
+const MAX_TIMEOUT_MINUTES: i64 = 28 * 24 * 60;
+
+impl Handler {
+    /// 違反を記録し、違反回数に応じて処分する
+    pub(super) async fn apply_strike(
+        &self,
+        ctx: &Context,
+        post: &Post,
+        alert: &Alert,
+    ) -> Result<()> {
+        let config = &self.app_config.strike;
+        // 設定で無効になっている
+        if config.window_hours <= 0 {
+            return Ok(());
+        }
+        let guild_id = match post.guild_id {
+            Some(guild_id) => guild_id,
+            None => return Ok(()),
+        };
+
+        // 違反を記録 (編集などで記録済みのメッセージは数えない)
+        let inserted = self
+            .history
+            .insert_violation(&ViolationRecord {
+                guild_id: post.guild_id,
+                channel_id: post.channel_id,
+                message_id: post.id,
+                user_id: post.author.id,
+                reason: alert.reason.to_string(),
+                timestamp: post.timestamp.unix_timestamp(),
+                access_restore_at: None,
+            })
+            .await?;
+        if !inserted {
+            return Ok(());
+        }
+
+        // 期間内の違反回数を数える
+        let since = (Utc::now() - Duration::hours(config.window_hours)).timestamp();
+        let count = self
+            .history
+            .count_violations(&post.guild_id, &post.author.id, since)
+            .await?;
+
+        // 違反回数に対応する一番重い処分を選ぶ
+        let step = match config
+            .steps
+            .iter()
+            .filter(|step| step.count <= count)
+            .max_by_key(|step| step.count)
+        {
+            Some(step) => step,
+            None => return Ok(()),
+        };
+
+        // 処分を実行
+        let locale = match config.report_channel {
+            Some(report_channel) => self.locale(report_channel, post.guild_id),
+            None => self.locale(post.channel_id, post.guild_id),
+        };
+        let action = match step.action {
+            StrikeAction::Timeout => {
+                let minutes = step.minutes.clamp(1, MAX_TIMEOUT_MINUTES);
+                let until = Timestamp::from_unix_timestamp(
+                    (Utc::now() + Duration::minutes(minutes)).timestamp(),
+                )
+                .map_err(|why| anyhow!("タイムアウト期限の計算に失敗: {:?}", why))?;
+                guild_id
+                    .edit_member(ctx, post.author.id, |member| {
+                        member.disable_communication_until_datetime(until)
+                    })
+                    .await
+                    .context("タイムアウトに失敗")?;
+
+                self.text(
+                    locale,
+                    "strike.action_timeout",
+                    &[("minutes", minutes.to_string())],
+                )
+            }
+            StrikeAction::RemoveAccess => {
+                post.channel_id
+                    .create_permission(
+                        ctx,
+                        &PermissionOverwrite {
+                            allow: Permissions::empty(),
+                            deny: Permissions::SEND_MESSAGES
+                                | Permissions::SEND_MESSAGES_IN_THREADS
+                                | Permissions::CREATE_PUBLIC_THREADS,
+                            kind: PermissionOverwriteType::Member(post.author.id),
+                        },
+                    )
+                    .await
+                    .context("投稿権限の剥奪に失敗")?;
+
+                // 投稿権限を戻す日時を記録する (Botが再起動しても期限が来たら戻す)
+                let access_restore_at = (step.minutes > 0)
+                    .then(|| (Utc::now() + Duration::minutes(step.minutes)).timestamp());
+                self.history
+                    .set_access_restore(
+                        &post.id,
+                        &post.channel_id,
+                        &post.author.id,
+                        access_restore_at,
+                    )
+                    .await?;
+
+                if step.minutes > 0 {
+                    self.text(
+                        locale,
+                        "strike.action_remove_access",
+                        &[
+                            ("channel", post.channel_id.mention().to_string()),
+                            ("minutes", step.minutes.to_string()),
+                        ],
+                    )
+                } else {
+                    self.text(
+                        locale,
+                        "strike.action_remove_access_permanent",
+                        &[("channel", post.channel_id.mention().to_string())],
+                    )
+                }
+            }
+        };
+
+        // モデレーターに報告
+        if let Some(report_channel) = config.report_channel {
+            let args = [
+                ("user", post.author.mention().to_string()),
+                ("count", count.to_string()),
+                ("hours", config.window_hours.to_string()),
+            ];
+            let link = post.id.link(post.message_channel_id(), post.guild_id);
+            report_channel
+                .send_message(ctx, |m| {
+                    m.embed(|e| {
+                        e.title(self.text(locale, "strike.title", &args));
+                        e.description(self.text(locale, "strike.description", &args));
+                        e.field(
+                            self.text(locale, "strike.reason_name", &[]),
+                            self.text(locale, &format!("{}.title", alert.reason), &[]),
+                            false,
+                        );
+                        e.field(self.text(locale, "strike.action_name", &[]), action, false);
+                        e.field(self.text(locale, "strike.message_name", &[]), link, false)
+                    })
+                })
+                .await
+                .context("処分の報告に失敗")?;
+        }
+
+        Ok(())
+    }
+    /// 期限を過ぎた投稿権限の剥奪を元に戻す
+    pub(super) async fn restore_expired_access(&self, ctx: &Context) -> Result<()> {
+        let records = self
+            .history
+            .get_due_access_restores(Utc::now().timestamp())
+            .await?;
+        for record in records {
+            // チャンネルが削除されている場合などは諦める
+            if let Err(why) = record
+                .channel_id
+                .delete_permission(ctx, PermissionOverwriteType::Member(record.user_id))
+                .await
+            {
+                error!("投稿権限の復元に失敗: {:?}", why);
+            }
+            self.history
+                .set_access_restore(
+                    &record.message_id,
+                    &record.channel_id,
+                    &record.user_id,
+                    None,
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
-use anyhow::{Context as _, Result};
+use anyhow::{anyhow, Context as _, Result};
+use std::collections::HashSet;
 use std::sync::Arc;
 
 use chrono::{Duration, Utc};
@@ -27,6 +28,151 @@ pub struct HistoryRecord {
     pub timestamp: i64,
     /// 削除済み
     pub deleted: bool,
+    /// メッセージのスレッドID (スレッド内の投稿の場合、channel_idは親チャンネル)
+    pub thread_id: Option<ChannelId>,
+}
+
+impl HistoryRecord {
+    /// メッセージが投稿されたチャンネルID (スレッド内の投稿の場合はスレッドID)
+    pub fn message_channel_id(&self) -> ChannelId {
+        self.thread_id.unwrap_or(self.channel_id)
+    }
+}
+
+/// 説明文の履歴のレコード
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct DescriptionRecord {
+    /// メッセージのギルドID
+    pub guild_id: Option<GuildId>,
+    /// メッセージのチャンネルID
+    pub channel_id: ChannelId,
+    /// メッセージID
+    pub message_id: MessageId,
+    /// 投稿者のID
+    pub user_id: UserId,
+    /// 正規化した説明文
+    pub fingerprint: String,
+    /// タイムスタンプ
+    pub timestamp: i64,
+}
+
+/// リストの種類
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GuildListKind {
+    /// 許可リスト (クールダウンを無視する)
+    Allow,
+    /// 禁止リスト (宣伝を禁止する)
+    Deny,
+}
+
+impl GuildListKind {
+    /// データベースに保存する文字列
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GuildListKind::Allow => "allow",
+            GuildListKind::Deny => "deny",
+        }
+    }
+
+    /// データベースの文字列からパースする
+    pub fn parse(kind: &str) -> Result<GuildListKind> {
+        match kind {
+            "allow" => Ok(GuildListKind::Allow),
+            "deny" => Ok(GuildListKind::Deny),
+            _ => Err(anyhow!("不明なリストの種類: {}", kind)),
+        }
+    }
+}
+
+/// 許可/禁止リストのレコード
+#[derive(Debug, PartialEq, Clone)]
+pub struct GuildListRecord {
+    /// リストを適用するギルドID
+    pub guild_id: GuildId,
+    /// 招待先のギルドID
+    pub invite_guild_id: GuildId,
+    /// リストの種類
+    pub kind: GuildListKind,
+    /// 理由
+    pub reason: Option<String>,
+    /// 登録したユーザーのID
+    pub user_id: UserId,
+    /// タイムスタンプ
+    pub timestamp: i64,
+}
+
+/// 違反のレコード
+#[derive(Debug, PartialEq, Clone)]
+pub struct ViolationRecord {
+    /// メッセージのギルドID
+    pub guild_id: Option<GuildId>,
+    /// メッセージのチャンネルID
+    pub channel_id: ChannelId,
+    /// メッセージID
+    pub message_id: MessageId,
+    /// 投稿者のID
+    pub user_id: UserId,
+    /// 違反の種類
+    pub reason: String,
+    /// タイムスタンプ
+    pub timestamp: i64,
+    /// 剥奪した投稿権限を戻す日時 (剥奪していない場合と無期限の場合はNone)
+    pub access_restore_at: Option<i64>,
+}
+
+/// 宣伝するサーバーの所属確認のレコード
+#[derive(Debug, PartialEq, Clone)]
+pub struct OwnershipRecord {
+    /// 確認を行ったギルドID
+    pub guild_id: GuildId,
+    /// 招待先のギルドID
+    pub invite_guild_id: GuildId,
+    /// ユーザーID
+    pub user_id: UserId,
+    /// 確認コード
+    pub code: String,
+    /// 確認済み
+    pub verified: bool,
+    /// タイムスタンプ
+    pub timestamp: i64,
+}
+
+/// 承認待ちの宣伝のレコード
+#[derive(Debug, PartialEq, Clone)]
+pub struct ApprovalRecord {
+    /// スタッフ用チャンネルに送った承認待ちメッセージのID
+    pub review_message_id: MessageId,
+    /// 投稿されたギルドID
+    pub guild_id: Option<GuildId>,
+    /// 投稿されたチャンネルID
+    pub channel_id: ChannelId,
+    /// 投稿者のID
+    pub user_id: UserId,
+    /// 本文
+    pub content: String,
+    /// 招待コードと招待先のギルドID
+    pub invites: Vec<(String, GuildId)>,
+    /// 正規化した説明文
+    pub fingerprint: String,
+    /// タイムスタンプ
+    pub timestamp: i64,
+}
+
+/// 宣伝できるようになった時に送るリマインダーのレコード
+#[derive(Debug, PartialEq, Clone)]
+pub struct ReminderRecord {
+    /// ユーザーID
+    pub user_id: UserId,
+    /// ギルドID
+    pub guild_id: Option<GuildId>,
+    /// 宣伝するチャンネルID
+    pub channel_id: ChannelId,
+    /// 宣伝するサーバーのギルドID (宣伝回数の上限の場合はNone)
+    pub invite_guild_id: Option<GuildId>,
+    /// 宣伝するサーバーの招待コード (宣伝回数の上限の場合はNone)
+    pub invite_code: Option<String>,
+    /// 通知する日時
+    pub due: i64,
 }
 
 /// 履歴を探すキー
@@ -37,6 +183,29 @@ pub enum HistoryFindKey {
     InviteGuildId(GuildId),
 }
 
+impl HistoryFindKey {
+    /// 履歴のレコードの招待コード、または招待コードのギルドIDと一致するか
+    pub fn matches(&self, record: &HistoryRecord) -> bool {
+        match self {
+            HistoryFindKey::InviteCode(invite_code) => record.invite_code == *invite_code,
+            HistoryFindKey::InviteGuildId(invite_guild_id) => {
+                record.invite_guild_id == *invite_guild_id
+            }
+        }
+    }
+}
+
+/// 再投稿で置き換えられる投稿を探すキー
+/// (min_per_user_start分以内に同じユーザーが同じチャンネルで同じ鯖を宣伝した投稿は、再投稿の時に削除される)
+pub struct RepostKey {
+    /// 投稿するチャンネルID
+    pub channel_id: ChannelId,
+    /// 投稿者のID
+    pub user_id: UserId,
+    /// 投稿する鯖の招待コードとギルドID
+    pub invites: Vec<HistoryFindKey>,
+}
+
 /// 履歴管理クラス
 pub struct HistoryLog {
     /// sql接続情報
@@ -52,6 +221,11 @@ impl HistoryLog {
         let conn = Connection::open(format!("{}/history_log.db", basedir))
             .context("履歴データベースのオープンに失敗")?;
 
+        Self::open(conn, ban_period)
+    }
+
+    /// 接続済みのデータベースにテーブルを作成する
+    fn open(conn: Connection, ban_period: BanPeriodConfig) -> Result<HistoryLog> {
         // テーブルを作成
         conn.execute(
             "CREATE TABLE IF NOT EXISTS history (
@@ -63,11 +237,102 @@ impl HistoryLog {
                 message_id       VARCHAR(20) NOT NULL,
                 user_id          VARCHAR(20) NOT NULL,
                 timestamp        TIMESTAMP   NOT NULL,
-                deleted          INTEGER     NOT NULL DEFAULT 0
+                deleted          INTEGER     NOT NULL DEFAULT 0,
+                thread_id        VARCHAR(20)
             )",
             params!(),
         )
         .context("履歴データベースの作成に失敗")?;
+        // スレッド対応前に作成されたデータベースにはthread_idを追加する
+        let has_thread_id = conn
+            .prepare("SELECT thread_id FROM history LIMIT 0")
+            .is_ok();
+        if !has_thread_id {
+            conn.execute(
+                "ALTER TABLE history ADD COLUMN thread_id VARCHAR(20)",
+                params!(),
+            )
+            .context("履歴データベースの更新に失敗")?;
+        }
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS description (
+                message_id       VARCHAR(20) PRIMARY KEY,
+                guild_id         VARCHAR(20),
+                channel_id       VARCHAR(20) NOT NULL,
+                user_id          VARCHAR(20) NOT NULL,
+                fingerprint      TEXT        NOT NULL,
+                timestamp        TIMESTAMP   NOT NULL
+            )",
+            params!(),
+        )
+        .context("説明文の履歴データベースの作成に失敗")?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS guild_list (
+                guild_id         VARCHAR(20) NOT NULL,
+                invite_guild_id  VARCHAR(20) NOT NULL,
+                kind             VARCHAR(10) NOT NULL,
+                reason           TEXT,
+                user_id          VARCHAR(20) NOT NULL,
+                timestamp        TIMESTAMP   NOT NULL,
+                PRIMARY KEY (guild_id, invite_guild_id)
+            )",
+            params!(),
+        )
+        .context("許可/禁止リストデータベースの作成に失敗")?;
+        // 編集で同じメッセージが何度も検証されるため、メッセージごとに1回だけ数える
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS violation (
+                id               INTEGER PRIMARY KEY AUTOINCREMENT,
+                guild_id         VARCHAR(20),
+                channel_id       VARCHAR(20) NOT NULL,
+                message_id       VARCHAR(20) NOT NULL UNIQUE,
+                user_id          VARCHAR(20) NOT NULL,
+                reason           VARCHAR(40) NOT NULL,
+                timestamp        TIMESTAMP   NOT NULL,
+                access_restore_at  TIMESTAMP
+            )",
+            params!(),
+        )
+        .context("違反履歴データベースの作成に失敗")?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS server_ownership (
+                guild_id         VARCHAR(20) NOT NULL,
+                invite_guild_id  VARCHAR(20) NOT NULL,
+                user_id          VARCHAR(20) NOT NULL,
+                code             VARCHAR(20) NOT NULL,
+                verified         INTEGER     NOT NULL DEFAULT 0,
+                timestamp        TIMESTAMP   NOT NULL,
+                PRIMARY KEY (guild_id, invite_guild_id, user_id)
+            )",
+            params!(),
+        )
+        .context("所属確認データベースの作成に失敗")?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS approval_queue (
+                review_message_id  VARCHAR(20) NOT NULL PRIMARY KEY,
+                guild_id           VARCHAR(20),
+                channel_id         VARCHAR(20) NOT NULL,
+                user_id            VARCHAR(20) NOT NULL,
+                content            TEXT        NOT NULL,
+                invites            TEXT        NOT NULL,
+                fingerprint        TEXT        NOT NULL,
+                timestamp          TIMESTAMP   NOT NULL
+            )",
+            params!(),
+        )
+        .context("承認待ちデータベースの作成に失敗")?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS reminder (
+                user_id          VARCHAR(20) NOT NULL,
+                guild_id         VARCHAR(20),
+                channel_id       VARCHAR(20) NOT NULL,
+                invite_guild_id  VARCHAR(20),
+                invite_code      VARCHAR(40),
+                due              TIMESTAMP   NOT NULL
+            )",
+            params!(),
+        )
+        .context("リマインダーデータベースの作成に失敗")?;
 
         // 初期化
         Ok(HistoryLog {
@@ -91,22 +356,21 @@ impl HistoryLog {
                 message_id,
                 user_id,
                 timestamp,
-                deleted
+                deleted,
+                thread_id
             )
             VALUES
-                (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
                 params!(
                     record.invite_code,
                     record.invite_guild_id.to_string(),
-                    match record.guild_id {
-                        Some(guild_id) => Some(guild_id.to_string()),
-                        None => None,
-                    },
+                    record.guild_id.map(|guild_id| guild_id.to_string()),
                     record.channel_id.to_string(),
                     record.message_id.to_string(),
                     record.user_id.to_string(),
                     record.timestamp,
                     record.deleted,
+                    record.thread_id.map(|thread_id| thread_id.to_string()),
                 ),
             )
             .with_context(|| format!("履歴データベースへの書き込みに失敗: {:?}", record))?;
@@ -132,6 +396,21 @@ impl HistoryLog {
             )
             .with_context(|| format!("履歴データベースからの削除に失敗: {:?}", message_id))?;
 
+        // 削除された投稿の説明文は重複の検出に使わない
+        self.conn
+            .lock()
+            .await
+            .execute(
+                "DELETE FROM
+                    description
+                WHERE
+                    message_id = ?1",
+                params!(message_id.to_string()),
+            )
+            .with_context(|| {
+                format!("説明文の履歴データベースからの削除に失敗: {:?}", message_id)
+            })?;
+
         self.conn
             .lock()
             .await
@@ -164,6 +443,7 @@ impl HistoryLog {
             let user_id: String = row.get(5)?;
             let timestamp: i64 = row.get(6)?;
             let deleted: i64 = row.get(7)?;
+            let thread_id: Option<String> = row.get(8)?;
             Ok((
                 invite_code,
                 invite_guild_id,
@@ -173,6 +453,7 @@ impl HistoryLog {
                 user_id,
                 timestamp,
                 deleted,
+                thread_id,
             ))
         })
         .map(|row| -> Result<HistoryRecord> {
@@ -186,6 +467,7 @@ impl HistoryLog {
                 user_id,
                 timestamp,
                 deleted,
+                thread_id,
             ) = row?;
             // パースして構造体を作る
             Ok(HistoryRecord {
@@ -200,6 +482,10 @@ impl HistoryLog {
                 user_id: UserId(user_id.parse()?),
                 timestamp,
                 deleted: deleted != 0,
+                thread_id: match thread_id {
+                    Some(thread_id) => Some(ChannelId(thread_id.parse()?)),
+                    None => None,
+                },
             })
         })
         .filter_map(|row| row.ok())
@@ -232,7 +518,8 @@ impl HistoryLog {
                 message_id,
                 user_id,
                 timestamp,
-                deleted
+                deleted,
+                thread_id
             FROM
                 history
             WHERE
@@ -292,7 +579,8 @@ impl HistoryLog {
                 message_id,
                 user_id,
                 timestamp,
-                deleted
+                deleted,
+                thread_id
             FROM
                 history
             WHERE
@@ -301,7 +589,7 @@ impl HistoryLog {
                 AND deleted = 0";
         // クエリを構築
         let mut stmt = conn
-            .prepare(&query)
+            .prepare(query)
             .with_context(|| format!("ユーザー履歴チェック用のSQL文の構築に失敗: {}", query))?;
         // クエリを実行
         let records = Self::rows_to_records(
@@ -314,4 +602,1322 @@ impl HistoryLog {
         .collect::<Vec<_>>();
         Ok(records)
     }
+
+    // 再投稿で置き換えられる投稿のメッセージIDを取得する
+    fn get_reposted_message_ids(
+        &self,
+        conn: &Connection,
+        repost: &RepostKey,
+    ) -> Result<HashSet<MessageId>> {
+        let query = "SELECT
+                invite_code,
+                invite_guild_id,
+                guild_id,
+                channel_id,
+                message_id,
+                user_id,
+                timestamp,
+                deleted,
+                thread_id
+            FROM
+                history
+            WHERE
+                channel_id = ?1
+                AND user_id = ?2
+                AND ?3 < timestamp";
+        // クエリを構築
+        let mut stmt = conn
+            .prepare(query)
+            .with_context(|| format!("再投稿チェック用のSQL文の構築に失敗: {}", query))?;
+        // min_per_user_start分以内を指定
+        let ban_period_user_start =
+            (Utc::now() - Duration::minutes(self.ban_period.min_per_user_start)).timestamp();
+        // クエリを実行し、同じ鯖を宣伝した投稿だけを残す
+        let message_ids = Self::rows_to_records(
+            stmt.query(params!(
+                repost.channel_id.to_string(),
+                repost.user_id.to_string(),
+                ban_period_user_start,
+            ))
+            .context("履歴データベースの読み込みに失敗")?,
+        )
+        .filter(|record| repost.invites.iter().any(|key| key.matches(record)))
+        .map(|record| record.message_id)
+        .collect();
+        Ok(message_ids)
+    }
+
+    // 指定時刻以降のユーザーの投稿のタイムスタンプを古い順に取得する (チャンネルを指定しない場合はギルド全体)
+    // 再投稿で置き換えられる投稿は数えない
+    pub async fn get_post_timestamps_by_user(
+        &self,
+        event_message_id: &MessageId,
+        guild_id: &Option<GuildId>,
+        channel_id: Option<&ChannelId>,
+        since: i64,
+        repost: &RepostKey,
+    ) -> Result<Vec<i64>> {
+        // データベースをロック
+        let conn = self.conn.lock().await;
+        let reposted = self.get_reposted_message_ids(&conn, repost)?;
+        // 1つの投稿に複数の招待リンクがある場合もあるため、メッセージごとにまとめる
+        let query = "SELECT
+                message_id,
+                MIN(timestamp)
+            FROM
+                history
+            WHERE
+                message_id != ?1
+                AND guild_id IS ?2
+                AND (?3 IS NULL OR channel_id = ?3)
+                AND user_id = ?4
+                AND ?5 < timestamp
+            GROUP BY
+                message_id
+            ORDER BY
+                MIN(timestamp)";
+        // クエリを構築
+        let mut stmt = conn
+            .prepare(query)
+            .with_context(|| format!("投稿回数チェック用のSQL文の構築に失敗: {}", query))?;
+        // クエリを実行
+        let timestamps = stmt
+            .query_map(
+                params!(
+                    event_message_id.to_string(),
+                    guild_id.map(|guild_id| guild_id.to_string()),
+                    channel_id.map(|channel_id| channel_id.to_string()),
+                    repost.user_id.to_string(),
+                    since,
+                ),
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)),
+            )
+            .context("履歴データベースの読み込みに失敗")?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("履歴データベースの読み込みに失敗")?
+            .into_iter()
+            .filter(|(message_id, _timestamp)| {
+                message_id.parse().map_or(true, |message_id| {
+                    !reposted.contains(&MessageId(message_id))
+                })
+            })
+            .map(|(_message_id, timestamp)| timestamp)
+            .collect();
+        Ok(timestamps)
+    }
+
+    // 説明文の履歴にレコードを登録する
+    pub async fn insert_description(&self, record: DescriptionRecord) -> Result<()> {
+        self.conn
+            .lock()
+            .await
+            .execute(
+                "REPLACE INTO description (
+                message_id,
+                guild_id,
+                channel_id,
+                user_id,
+                fingerprint,
+                timestamp
+            )
+            VALUES
+                (?1, ?2, ?3, ?4, ?5, ?6)",
+                params!(
+                    record.message_id.to_string(),
+                    record.guild_id.map(|guild_id| guild_id.to_string()),
+                    record.channel_id.to_string(),
+                    record.user_id.to_string(),
+                    record.fingerprint,
+                    record.timestamp,
+                ),
+            )
+            .with_context(|| format!("説明文の履歴データベースへの書き込みに失敗: {:?}", record))?;
+
+        Ok(())
+    }
+
+    // 指定時刻以降の説明文の履歴を取得する (再投稿で置き換えられる投稿は除く)
+    pub async fn get_descriptions(
+        &self,
+        event_message_id: &MessageId,
+        guild_id: &Option<GuildId>,
+        since: i64,
+        repost: &RepostKey,
+    ) -> Result<Vec<DescriptionRecord>> {
+        // データベースをロック
+        let conn = self.conn.lock().await;
+        let reposted = self.get_reposted_message_ids(&conn, repost)?;
+        let query = "SELECT
+                message_id,
+                guild_id,
+                channel_id,
+                user_id,
+                fingerprint,
+                timestamp
+            FROM
+                description
+            WHERE
+                message_id != ?1
+                AND guild_id IS ?2
+                AND ?3 < timestamp";
+        // クエリを構築
+        let mut stmt = conn
+            .prepare(query)
+            .with_context(|| format!("説明文の履歴取得用のSQL文の構築に失敗: {}", query))?;
+        // クエリを実行
+        let records = stmt
+            .query(params!(
+                event_message_id.to_string(),
+                guild_id.map(|guild_id| guild_id.to_string()),
+                since,
+            ))
+            .context("説明文の履歴データベースの読み込みに失敗")?
+            .mapped(|row| {
+                // レコードの要素をSQLから取得
+                let message_id: String = row.get(0)?;
+                let guild_id: Option<String> = row.get(1)?;
+                let channel_id: String = row.get(2)?;
+                let user_id: String = row.get(3)?;
+                let fingerprint: String = row.get(4)?;
+                let timestamp: i64 = row.get(5)?;
+                Ok((
+                    message_id,
+                    guild_id,
+                    channel_id,
+                    user_id,
+                    fingerprint,
+                    timestamp,
+                ))
+            })
+            .map(|row| -> Result<DescriptionRecord> {
+                // 未パースの文字変数を展開
+                let (message_id, guild_id, channel_id, user_id, fingerprint, timestamp) = row?;
+                // パースして構造体を作る
+                Ok(DescriptionRecord {
+                    guild_id: match guild_id {
+                        Some(guild_id) => Some(GuildId(guild_id.parse()?)),
+                        None => None,
+                    },
+                    channel_id: ChannelId(channel_id.parse()?),
+                    message_id: MessageId(message_id.parse()?),
+                    user_id: UserId(user_id.parse()?),
+                    fingerprint,
+                    timestamp,
+                })
+            })
+            .filter_map(|row| row.ok())
+            .filter(|record| !reposted.contains(&record.message_id))
+            .collect::<Vec<_>>();
+        Ok(records)
+    }
+
+    // リストにレコードを登録する (既に登録されていれば上書きする)
+    pub async fn insert_guild_list(&self, record: GuildListRecord) -> Result<()> {
+        self.conn
+            .lock()
+            .await
+            .execute(
+                "REPLACE INTO guild_list (
+                guild_id,
+                invite_guild_id,
+                kind,
+                reason,
+                user_id,
+                timestamp
+            )
+            VALUES
+                (?1, ?2, ?3, ?4, ?5, ?6)",
+                params!(
+                    record.guild_id.to_string(),
+                    record.invite_guild_id.to_string(),
+                    record.kind.as_str(),
+                    record.reason,
+                    record.user_id.to_string(),
+                    record.timestamp,
+                ),
+            )
+            .with_context(|| {
+                format!(
+                    "許可/禁止リストデータベースへの書き込みに失敗: {:?}",
+                    record
+                )
+            })?;
+
+        Ok(())
+    }
+
+    // リストからレコードを削除する (削除できたかを返す)
+    pub async fn delete_guild_list(
+        &self,
+        guild_id: &GuildId,
+        invite_guild_id: &GuildId,
+    ) -> Result<bool> {
+        let count = self
+            .conn
+            .lock()
+            .await
+            .execute(
+                "DELETE FROM
+                    guild_list
+                WHERE
+                    guild_id = ?1
+                    AND invite_guild_id = ?2",
+                params!(guild_id.to_string(), invite_guild_id.to_string()),
+            )
+            .with_context(|| {
+                format!(
+                    "許可/禁止リストデータベースからの削除に失敗: {:?}",
+                    invite_guild_id
+                )
+            })?;
+
+        Ok(count > 0)
+    }
+
+    // RowsからGuildListRecordを生成する
+    fn rows_to_guild_list_records(rows: Rows<'_>) -> impl Iterator<Item = GuildListRecord> + '_ {
+        rows.mapped(|row| {
+            // レコードの要素をSQLから取得
+            let guild_id: String = row.get(0)?;
+            let invite_guild_id: String = row.get(1)?;
+            let kind: String = row.get(2)?;
+            let reason: Option<String> = row.get(3)?;
+            let user_id: String = row.get(4)?;
+            let timestamp: i64 = row.get(5)?;
+            Ok((guild_id, invite_guild_id, kind, reason, user_id, timestamp))
+        })
+        .map(|row| -> Result<GuildListRecord> {
+            // 未パースの文字変数を展開
+            let (guild_id, invite_guild_id, kind, reason, user_id, timestamp) = row?;
+            // パースして構造体を作る
+            Ok(GuildListRecord {
+                guild_id: GuildId(guild_id.parse()?),
+                invite_guild_id: GuildId(invite_guild_id.parse()?),
+                kind: GuildListKind::parse(&kind)?,
+                reason,
+                user_id: UserId(user_id.parse()?),
+                timestamp,
+            })
+        })
+        .filter_map(|row| row.ok())
+    }
+
+    // 招待先のギルドがリストに登録されているか取得する
+    pub async fn get_guild_list(
+        &self,
+        guild_id: &Option<GuildId>,
+        invite_guild_id: &GuildId,
+    ) -> Result<Option<GuildListRecord>> {
+        // データベースをロック
+        let conn = self.conn.lock().await;
+        let query = "SELECT
+                guild_id,
+                invite_guild_id,
+                kind,
+                reason,
+                user_id,
+                timestamp
+            FROM
+                guild_list
+            WHERE
+                guild_id = ?1
+                AND invite_guild_id = ?2";
+        // クエリを構築
+        let mut stmt = conn
+            .prepare(query)
+            .with_context(|| format!("許可/禁止リスト検索用のSQL文の構築に失敗: {}", query))?;
+        // クエリを実行
+        let record = Self::rows_to_guild_list_records(
+            stmt.query(params!(
+                guild_id.map(|guild_id| guild_id.to_string()),
+                invite_guild_id.to_string(),
+            ))
+            .context("許可/禁止リストデータベースの読み込みに失敗")?,
+        )
+        .next();
+        Ok(record)
+    }
+
+    // ギルドのリストをすべて取得する
+    pub async fn get_guild_lists(&self, guild_id: &GuildId) -> Result<Vec<GuildListRecord>> {
+        // データベースをロック
+        let conn = self.conn.lock().await;
+        let query = "SELECT
+                guild_id,
+                invite_guild_id,
+                kind,
+                reason,
+                user_id,
+                timestamp
+            FROM
+                guild_list
+            WHERE
+                guild_id = ?1
+            ORDER BY
+                kind, timestamp";
+        // クエリを構築
+        let mut stmt = conn
+            .prepare(query)
+            .with_context(|| format!("許可/禁止リスト取得用のSQL文の構築に失敗: {}", query))?;
+        // クエリを実行
+        let records = Self::rows_to_guild_list_records(
+            stmt.query(params!(guild_id.to_string()))
+                .context("許可/禁止リストデータベースの読み込みに失敗")?,
+        )
+        .collect::<Vec<_>>();
+        Ok(records)
+    }
+
+    // 違反を記録する (既に記録済みのメッセージであればfalseを返す)
+    pub async fn insert_violation(&self, record: &ViolationRecord) -> Result<bool> {
+        let count = self
+            .conn
+            .lock()
+            .await
+            .execute(
+                "INSERT OR IGNORE INTO violation (
+                guild_id,
+                channel_id,
+                message_id,
+                user_id,
+                reason,
+                timestamp,
+                access_restore_at
+            )
+            VALUES
+                (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params!(
+                    record.guild_id.map(|guild_id| guild_id.to_string()),
+                    record.channel_id.to_string(),
+                    record.message_id.to_string(),
+                    record.user_id.to_string(),
+                    record.reason,
+                    record.timestamp,
+                    record.access_restore_at,
+                ),
+            )
+            .with_context(|| format!("違反履歴データベースへの書き込みに失敗: {:?}", record))?;
+
+        Ok(count > 0)
+    }
+
+    // 指定時刻以降のユーザーの違反回数を数える
+    pub async fn count_violations(
+        &self,
+        guild_id: &Option<GuildId>,
+        user_id: &UserId,
+        since: i64,
+    ) -> Result<u64> {
+        let count: i64 = self
+            .conn
+            .lock()
+            .await
+            .query_row(
+                "SELECT
+                    COUNT(*)
+                FROM
+                    violation
+                WHERE
+                    guild_id IS ?1
+                    AND user_id = ?2
+                    AND timestamp >= ?3",
+                params!(
+                    guild_id.map(|guild_id| guild_id.to_string()),
+                    user_id.to_string(),
+                    since,
+                ),
+                |row| row.get(0),
+            )
+            .context("違反履歴データベースの読み込みに失敗")?;
+
+        Ok(count as u64)
+    }
+
+    // 違反の記録を取り消す (剥奪した投稿権限を戻す予定があれば、その記録を返す)
+    pub async fn delete_violation(
+        &self,
+        message_id: &MessageId,
+    ) -> Result<Option<ViolationRecord>> {
+        let record = self
+            .get_due_access_restores(i64::MAX)
+            .await?
+            .into_iter()
+            .find(|record| record.message_id == *message_id);
+        self.conn
+            .lock()
+            .await
+            .execute(
+                "DELETE FROM violation WHERE message_id = ?1",
+                params!(message_id.to_string()),
+            )
+            .with_context(|| format!("違反履歴データベースからの削除に失敗: {:?}", message_id))?;
+
+        Ok(record)
+    }
+
+    // 投稿権限を戻す日時を設定する (同じチャンネルで以前に剥奪した分の予定は取り消す)
+    pub async fn set_access_restore(
+        &self,
+        message_id: &MessageId,
+        channel_id: &ChannelId,
+        user_id: &UserId,
+        access_restore_at: Option<i64>,
+    ) -> Result<()> {
+        let conn = self.conn.lock().await;
+        conn.execute(
+            "UPDATE
+                violation
+            SET
+                access_restore_at = NULL
+            WHERE
+                channel_id = ?1
+                AND user_id = ?2",
+            params!(channel_id.to_string(), user_id.to_string()),
+        )
+        .with_context(|| {
+            format!(
+                "違反履歴データベースで権限を戻す予定の取り消しに失敗: {:?}",
+                message_id
+            )
+        })?;
+        conn.execute(
+            "UPDATE
+                violation
+            SET
+                access_restore_at = ?2
+            WHERE
+                message_id = ?1",
+            params!(message_id.to_string(), access_restore_at),
+        )
+        .with_context(|| {
+            format!(
+                "違反履歴データベースで権限を戻す日時の設定に失敗: {:?}",
+                message_id
+            )
+        })?;
+
+        Ok(())
+    }
+
+    // 投稿権限を戻す日時を過ぎた違反を取得する
+    pub async fn get_due_access_restores(&self, now: i64) -> Result<Vec<ViolationRecord>> {
+        // データベースをロック
+        let conn = self.conn.lock().await;
+        let query = "SELECT
+                guild_id,
+                channel_id,
+                message_id,
+                user_id,
+                reason,
+                timestamp,
+                access_restore_at
+            FROM
+                violation
+            WHERE
+                access_restore_at <= ?1";
+        // クエリを構築
+        let mut stmt = conn
+            .prepare(query)
+            .with_context(|| format!("権限を戻す違反の検索用のSQL文の構築に失敗: {}", query))?;
+        // クエリを実行
+        let records = stmt
+            .query(params!(now))
+            .context("違反履歴データベースの読み込みに失敗")?
+            .mapped(|row| {
+                // レコードの要素をSQLから取得
+                let guild_id: Option<String> = row.get(0)?;
+                let channel_id: String = row.get(1)?;
+                let message_id: String = row.get(2)?;
+                let user_id: String = row.get(3)?;
+                let reason: String = row.get(4)?;
+                let timestamp: i64 = row.get(5)?;
+                let access_restore_at: Option<i64> = row.get(6)?;
+                Ok((
+                    guild_id,
+                    channel_id,
+                    message_id,
+                    user_id,
+                    reason,
+                    timestamp,
+                    access_restore_at,
+                ))
+            })
+            .map(|row| -> Result<ViolationRecord> {
+                // 未パースの文字変数を展開
+                let (
+                    guild_id,
+                    channel_id,
+                    message_id,
+                    user_id,
+                    reason,
+                    timestamp,
+                    access_restore_at,
+                ) = row?;
+                // パースして構造体を作る
+                Ok(ViolationRecord {
+                    guild_id: match guild_id {
+                        Some(guild_id) => Some(GuildId(guild_id.parse()?)),
+                        None => None,
+                    },
+                    channel_id: ChannelId(channel_id.parse()?),
+                    message_id: MessageId(message_id.parse()?),
+                    user_id: UserId(user_id.parse()?),
+                    reason,
+                    timestamp,
+                    access_restore_at,
+                })
+            })
+            .filter_map(|row| row.ok())
+            .collect::<Vec<_>>();
+        Ok(records)
+    }
+
+    // レコードを登録する (既に登録されていれば上書きする)
+    pub async fn insert_ownership(&self, record: OwnershipRecord) -> Result<()> {
+        self.conn
+            .lock()
+            .await
+            .execute(
+                "REPLACE INTO server_ownership (
+                guild_id,
+                invite_guild_id,
+                user_id,
+                code,
+                verified,
+                timestamp
+            )
+            VALUES
+                (?1, ?2, ?3, ?4, ?5, ?6)",
+                params!(
+                    record.guild_id.to_string(),
+                    record.invite_guild_id.to_string(),
+                    record.user_id.to_string(),
+                    record.code,
+                    record.verified,
+                    record.timestamp,
+                ),
+            )
+            .with_context(|| format!("所属確認データベースへの書き込みに失敗: {:?}", record))?;
+
+        Ok(())
+    }
+
+    // RowsからOwnershipRecordを生成する
+    fn rows_to_ownership_records(rows: Rows<'_>) -> impl Iterator<Item = OwnershipRecord> + '_ {
+        rows.mapped(|row| {
+            // レコードの要素をSQLから取得
+            let guild_id: String = row.get(0)?;
+            let invite_guild_id: String = row.get(1)?;
+            let user_id: String = row.get(2)?;
+            let code: String = row.get(3)?;
+            let verified: i64 = row.get(4)?;
+            let timestamp: i64 = row.get(5)?;
+            Ok((
+                guild_id,
+                invite_guild_id,
+                user_id,
+                code,
+                verified,
+                timestamp,
+            ))
+        })
+        .map(|row| -> Result<OwnershipRecord> {
+            // 未パースの文字変数を展開
+            let (guild_id, invite_guild_id, user_id, code, verified, timestamp) = row?;
+            // パースして構造体を作る
+            Ok(OwnershipRecord {
+                guild_id: GuildId(guild_id.parse()?),
+                invite_guild_id: GuildId(invite_guild_id.parse()?),
+                user_id: UserId(user_id.parse()?),
+                code,
+                verified: verified != 0,
+                timestamp,
+            })
+        })
+        .filter_map(|row| row.ok())
+    }
+
+    // ユーザーの招待先ギルドの所属確認を取得する
+    pub async fn get_ownership(
+        &self,
+        guild_id: &Option<GuildId>,
+        invite_guild_id: &GuildId,
+        user_id: &UserId,
+    ) -> Result<Option<OwnershipRecord>> {
+        // データベースをロック
+        let conn = self.conn.lock().await;
+        let query = "SELECT
+                guild_id,
+                invite_guild_id,
+                user_id,
+                code,
+                verified,
+                timestamp
+            FROM
+                server_ownership
+            WHERE
+                guild_id = ?1
+                AND invite_guild_id = ?2
+                AND user_id = ?3";
+        // クエリを構築
+        let mut stmt = conn
+            .prepare(query)
+            .with_context(|| format!("所属確認検索用のSQL文の構築に失敗: {}", query))?;
+        // クエリを実行
+        let record = Self::rows_to_ownership_records(
+            stmt.query(params!(
+                guild_id.map(|guild_id| guild_id.to_string()),
+                invite_guild_id.to_string(),
+                user_id.to_string(),
+            ))
+            .context("所属確認データベースの読み込みに失敗")?,
+        )
+        .next();
+        Ok(record)
+    }
+
+    // レコードを登録する
+    pub async fn insert_approval(&self, record: &ApprovalRecord) -> Result<()> {
+        // 招待は1行に1つずつ保存する
+        let invites = record
+            .invites
+            .iter()
+            .map(|(code, guild_id)| format!("{} {}", code, guild_id))
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.conn
+            .lock()
+            .await
+            .execute(
+                "INSERT INTO approval_queue (
+                review_message_id,
+                guild_id,
+                channel_id,
+                user_id,
+                content,
+                invites,
+                fingerprint,
+                timestamp
+            )
+            VALUES
+                (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params!(
+                    record.review_message_id.to_string(),
+                    record.guild_id.map(|guild_id| guild_id.to_string()),
+                    record.channel_id.to_string(),
+                    record.user_id.to_string(),
+                    record.content,
+                    invites,
+                    record.fingerprint,
+                    record.timestamp,
+                ),
+            )
+            .with_context(|| format!("承認待ちデータベースへの書き込みに失敗: {:?}", record))?;
+
+        Ok(())
+    }
+
+    // RowsからApprovalRecordを生成する
+    fn rows_to_approval_records(rows: Rows<'_>) -> impl Iterator<Item = ApprovalRecord> + '_ {
+        rows.mapped(|row| {
+            // レコードの要素をSQLから取得
+            let review_message_id: String = row.get(0)?;
+            let guild_id: Option<String> = row.get(1)?;
+            let channel_id: String = row.get(2)?;
+            let user_id: String = row.get(3)?;
+            let content: String = row.get(4)?;
+            let invites: String = row.get(5)?;
+            let fingerprint: String = row.get(6)?;
+            let timestamp: i64 = row.get(7)?;
+            Ok((
+                review_message_id,
+                guild_id,
+                channel_id,
+                user_id,
+                content,
+                invites,
+                fingerprint,
+                timestamp,
+            ))
+        })
+        .map(|row| -> Result<ApprovalRecord> {
+            // 未パースの文字変数を展開
+            let (
+                review_message_id,
+                guild_id,
+                channel_id,
+                user_id,
+                content,
+                invites,
+                fingerprint,
+                timestamp,
+            ) = row?;
+            // 招待コードと招待先のギルドIDをパース
+            let invites = invites
+                .lines()
+                .filter_map(|line| line.split_once(' '))
+                .map(|(code, guild_id)| Ok((code.to_string(), GuildId(guild_id.parse()?))))
+                .collect::<Result<Vec<_>>>()?;
+            // パースして構造体を作る
+            Ok(ApprovalRecord {
+                review_message_id: MessageId(review_message_id.parse()?),
+                guild_id: match guild_id {
+                    Some(guild_id) => Some(GuildId(guild_id.parse()?)),
+                    None => None,
+                },
+                channel_id: ChannelId(channel_id.parse()?),
+                user_id: UserId(user_id.parse()?),
+                content,
+                invites,
+                fingerprint,
+                timestamp,
+            })
+        })
+        .filter_map(|row| row.ok())
+    }
+
+    // 承認待ちメッセージのIDからレコードを取得する
+    pub async fn get_approval(
+        &self,
+        review_message_id: &MessageId,
+    ) -> Result<Option<ApprovalRecord>> {
+        // データベースをロック
+        let conn = self.conn.lock().await;
+        let query = "SELECT
+                review_message_id,
+                guild_id,
+                channel_id,
+                user_id,
+                content,
+                invites,
+                fingerprint,
+                timestamp
+            FROM
+                approval_queue
+            WHERE
+                review_message_id = ?1";
+        // クエリを構築
+        let mut stmt = conn
+            .prepare(query)
+            .with_context(|| format!("承認待ち検索用のSQL文の構築に失敗: {}", query))?;
+        // クエリを実行
+        let record = Self::rows_to_approval_records(
+            stmt.query(params!(review_message_id.to_string()))
+                .context("承認待ちデータベースの読み込みに失敗")?,
+        )
+        .next();
+        Ok(record)
+    }
+
+    // レコードを削除する (削除した場合はtrue)
+    pub async fn delete_approval(&self, review_message_id: &MessageId) -> Result<bool> {
+        let deleted = self
+            .conn
+            .lock()
+            .await
+            .execute(
+                "DELETE FROM approval_queue WHERE review_message_id = ?1",
+                params!(review_message_id.to_string()),
+            )
+            .with_context(|| {
+                format!(
+                    "承認待ちデータベースからの削除に失敗: {}",
+                    review_message_id
+                )
+            })?;
+
+        Ok(deleted > 0)
+    }
+
+    // リマインダーを登録する (同じチャンネル、同じサーバーのリマインダーがあれば置き換える)
+    pub async fn insert_reminder(&self, record: &ReminderRecord) -> Result<()> {
+        let conn = self.conn.lock().await;
+        conn.execute(
+            "DELETE FROM
+                reminder
+            WHERE
+                user_id = ?1
+                AND channel_id = ?2
+                AND invite_guild_id IS ?3",
+            params!(
+                record.user_id.to_string(),
+                record.channel_id.to_string(),
+                record
+                    .invite_guild_id
+                    .map(|invite_guild_id| invite_guild_id.to_string()),
+            ),
+        )
+        .with_context(|| format!("リマインダーデータベースからの削除に失敗: {:?}", record))?;
+        conn.execute(
+            "INSERT INTO reminder (
+                user_id,
+                guild_id,
+                channel_id,
+                invite_guild_id,
+                invite_code,
+                due
+            )
+            VALUES
+                (?1, ?2, ?3, ?4, ?5, ?6)",
+            params!(
+                record.user_id.to_string(),
+                record.guild_id.map(|guild_id| guild_id.to_string()),
+                record.channel_id.to_string(),
+                record
+                    .invite_guild_id
+                    .map(|invite_guild_id| invite_guild_id.to_string()),
+                record.invite_code,
+                record.due,
+            ),
+        )
+        .with_context(|| format!("リマインダーデータベースへの書き込みに失敗: {:?}", record))?;
+
+        Ok(())
+    }
+
+    // RowsからReminderRecordを生成する
+    fn rows_to_reminder_records(rows: Rows<'_>) -> impl Iterator<Item = ReminderRecord> + '_ {
+        rows.mapped(|row| {
+            // レコードの要素をSQLから取得
+            let user_id: String = row.get(0)?;
+            let guild_id: Option<String> = row.get(1)?;
+            let channel_id: String = row.get(2)?;
+            let invite_guild_id: Option<String> = row.get(3)?;
+            let invite_code: Option<String> = row.get(4)?;
+            let due: i64 = row.get(5)?;
+            Ok((
+                user_id,
+                guild_id,
+                channel_id,
+                invite_guild_id,
+                invite_code,
+                due,
+            ))
+        })
+        .map(|row| -> Result<ReminderRecord> {
+            // 未パースの文字変数を展開
+            let (user_id, guild_id, channel_id, invite_guild_id, invite_code, due) = row?;
+            // パースして構造体を作る
+            Ok(ReminderRecord {
+                user_id: UserId(user_id.parse()?),
+                guild_id: match guild_id {
+                    Some(guild_id) => Some(GuildId(guild_id.parse()?)),
+                    None => None,
+                },
+                channel_id: ChannelId(channel_id.parse()?),
+                invite_guild_id: match invite_guild_id {
+                    Some(invite_guild_id) => Some(GuildId(invite_guild_id.parse()?)),
+                    None => None,
+                },
+                invite_code,
+                due,
+            })
+        })
+        .filter_map(|row| row.ok())
+    }
+
+    // 通知する日時を過ぎたリマインダーを取得する
+    pub async fn get_due_reminders(&self, now: i64) -> Result<Vec<ReminderRecord>> {
+        // データベースをロック
+        let conn = self.conn.lock().await;
+        let query = "SELECT
+                user_id,
+                guild_id,
+                channel_id,
+                invite_guild_id,
+                invite_code,
+                due
+            FROM
+                reminder
+            WHERE
+                due <= ?1";
+        // クエリを構築
+        let mut stmt = conn
+            .prepare(query)
+            .with_context(|| format!("リマインダー検索用のSQL文の構築に失敗: {}", query))?;
+        // クエリを実行
+        let records = Self::rows_to_reminder_records(
+            stmt.query(params!(now))
+                .context("リマインダーデータベースの読み込みに失敗")?,
+        )
+        .collect::<Vec<_>>();
+        Ok(records)
+    }
+
+    // 送信したリマインダーを削除する (送信中に登録し直されたものは残す)
+    pub async fn delete_reminder(&self, record: &ReminderRecord) -> Result<()> {
+        self.conn
+            .lock()
+            .await
+            .execute(
+                "DELETE FROM
+                    reminder
+                WHERE
+                    user_id = ?1
+                    AND channel_id = ?2
+                    AND invite_guild_id IS ?3
+                    AND due = ?4",
+                params!(
+                    record.user_id.to_string(),
+                    record.channel_id.to_string(),
+                    record
+                        .invite_guild_id
+                        .map(|invite_guild_id| invite_guild_id.to_string()),
+                    record.due,
+                ),
+            )
+            .with_context(|| format!("リマインダーデータベースからの削除に失敗: {:?}", record))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    /// 投稿されたギルドID
+    const GUILD_ID: GuildId = GuildId(1);
+    /// 投稿されたチャンネルID
+    const CHANNEL_ID: ChannelId = ChannelId(2);
+    /// 投稿者のID
+    const USER_ID: UserId = UserId(3);
+
+    /// メモリ上のデータベースを作成する (再投稿できるのは10分以内)
+    fn open_history() -> HistoryLog {
+        let ban_period = BanPeriodConfig {
+            day: 7,
+            day_per_user: 7,
+            min_per_user_start: 10,
+        };
+        HistoryLog::open(Connection::open_in_memory().unwrap(), ban_period).unwrap()
+    }
+
+    /// 指定した分数だけ前のタイムスタンプ
+    fn minutes_ago(minutes: i64) -> i64 {
+        (Utc::now() - Duration::minutes(minutes)).timestamp()
+    }
+
+    /// 投稿者が鯖を宣伝した履歴のレコード
+    fn history_record(message_id: u64, invite_code: &str, invite_guild_id: u64) -> HistoryRecord {
+        HistoryRecord {
+            invite_code: invite_code.to_string(),
+            invite_guild_id: GuildId(invite_guild_id),
+            guild_id: Some(GUILD_ID),
+            channel_id: CHANNEL_ID,
+            message_id: MessageId(message_id),
+            user_id: USER_ID,
+            timestamp: minutes_ago(1),
+            ..Default::default()
+        }
+    }
+
+    /// 投稿者が同じチャンネルに投稿する時の再投稿のキー
+    fn repost_key(invites: Vec<HistoryFindKey>) -> RepostKey {
+        RepostKey {
+            channel_id: CHANNEL_ID,
+            user_id: USER_ID,
+            invites,
+        }
+    }
+
+    #[test]
+    fn test_quota_counts_other_servers_posted_recently() {
+        block_on(async {
+            let history = open_history();
+            // 再投稿できる時間内に別々の鯖を続けて宣伝する
+            history.insert(history_record(10, "a", 100)).await.unwrap();
+            history.insert(history_record(11, "b", 101)).await.unwrap();
+            history.insert(history_record(12, "c", 102)).await.unwrap();
+
+            // 4つ目の鯖の投稿では、直前の投稿もすべて数える
+            let repost = repost_key(vec![
+                HistoryFindKey::InviteCode("d".to_string()),
+                HistoryFindKey::InviteGuildId(GuildId(103)),
+            ]);
+            let timestamps = history
+                .get_post_timestamps_by_user(
+                    &MessageId(20),
+                    &Some(GUILD_ID),
+                    None,
+                    minutes_ago(60),
+                    &repost,
+                )
+                .await
+                .unwrap();
+            assert_eq!(timestamps.len(), 3);
+        });
+    }
+
+    #[test]
+    fn test_quota_skips_repost_of_same_server() {
+        block_on(async {
+            let history = open_history();
+            // 招待リンクを2つ含む投稿
+            history.insert(history_record(10, "a", 100)).await.unwrap();
+            history.insert(history_record(10, "e", 104)).await.unwrap();
+            history.insert(history_record(11, "b", 101)).await.unwrap();
+            // 再投稿できる時間を過ぎた同じ鯖の投稿
+            let expired = minutes_ago(30);
+            history
+                .insert(HistoryRecord {
+                    timestamp: expired,
+                    ..history_record(12, "x", 104)
+                })
+                .await
+                .unwrap();
+
+            // 別の招待コードでも同じ鯖の再投稿なら、その投稿だけを数えない
+            let repost = repost_key(vec![
+                HistoryFindKey::InviteCode("x".to_string()),
+                HistoryFindKey::InviteGuildId(GuildId(104)),
+            ]);
+            let timestamps = history
+                .get_post_timestamps_by_user(
+                    &MessageId(20),
+                    &Some(GUILD_ID),
+                    None,
+                    minutes_ago(60),
+                    &repost,
+                )
+                .await
+                .unwrap();
+            assert_eq!(timestamps.len(), 2);
+            assert_eq!(timestamps[0], expired);
+
+            // 別のチャンネルの投稿は再投稿で削除されないため数える
+            let repost = RepostKey {
+                channel_id: ChannelId(5),
+                ..repost
+            };
+            let timestamps = history
+                .get_post_timestamps_by_user(
+                    &MessageId(20),
+                    &Some(GUILD_ID),
+                    None,
+                    minutes_ago(60),
+                    &repost,
+                )
+                .await
+                .unwrap();
+            assert_eq!(timestamps.len(), 3);
+        });
+    }
+
+    #[test]
+    fn test_descriptions_skip_only_reposted_record() {
+        block_on(async {
+            let history = open_history();
+            // 再投稿できる時間内に別々の鯖を同じ説明文で宣伝する
+            for (message_id, invite_code, invite_guild_id) in [(10, "a", 100), (11, "b", 101)] {
+                history
+                    .insert(history_record(message_id, invite_code, invite_guild_id))
+                    .await
+                    .unwrap();
+                history
+                    .insert_description(DescriptionRecord {
+                        guild_id: Some(GUILD_ID),
+                        channel_id: CHANNEL_ID,
+                        message_id: MessageId(message_id),
+                        user_id: USER_ID,
+                        fingerprint: "楽しいサーバーです".to_string(),
+                        timestamp: minutes_ago(1),
+                    })
+                    .await
+                    .unwrap();
+            }
+
+            // 鯖aの再投稿では、鯖aの投稿だけを比べない
+            let repost = repost_key(vec![HistoryFindKey::InviteGuildId(GuildId(100))]);
+            let records = history
+                .get_descriptions(&MessageId(20), &Some(GUILD_ID), minutes_ago(60), &repost)
+                .await
+                .unwrap();
+            let message_ids = records
+                .iter()
+                .map(|record| record.message_id)
+                .collect::<Vec<_>>();
+            assert_eq!(message_ids, [MessageId(11)]);
+        });
+    }
+
+    /// 投稿者の違反のレコード
+    fn violation_record(message_id: u64, timestamp: i64) -> ViolationRecord {
+        ViolationRecord {
+            guild_id: Some(GUILD_ID),
+            channel_id: CHANNEL_ID,
+            message_id: MessageId(message_id),
+            user_id: USER_ID,
+            reason: "quota".to_string(),
+            timestamp,
+            access_restore_at: None,
+        }
+    }
+
+    #[test]
+    fn test_violations_count_each_message_once() {
+        block_on(async {
+            let history = open_history();
+            // 編集で同じメッセージが再び違反しても1回として数える
+            assert!(history
+                .insert_violation(&violation_record(10, minutes_ago(1)))
+                .await
+                .unwrap());
+            assert!(!history
+                .insert_violation(&violation_record(10, minutes_ago(1)))
+                .await
+                .unwrap());
+            assert!(history
+                .insert_violation(&violation_record(11, minutes_ago(1)))
+                .await
+                .unwrap());
+            // 数える時間より前の違反
+            assert!(history
+                .insert_violation(&violation_record(12, minutes_ago(120)))
+                .await
+                .unwrap());
+
+            let count = history
+                .count_violations(&Some(GUILD_ID), &USER_ID, minutes_ago(60))
+                .await
+                .unwrap();
+            assert_eq!(count, 2);
+
+            // 違反を取り消すと数えない
+            history.delete_violation(&MessageId(11)).await.unwrap();
+            let count = history
+                .count_violations(&Some(GUILD_ID), &USER_ID, minutes_ago(60))
+                .await
+                .unwrap();
+            assert_eq!(count, 1);
+        });
+    }
+
+    #[test]
+    fn test_access_restore_keeps_only_latest_schedule() {
+        block_on(async {
+            let history = open_history();
+            history
+                .insert_violation(&violation_record(10, minutes_ago(30)))
+                .await
+                .unwrap();
+            history
+                .insert_violation(&violation_record(11, minutes_ago(1)))
+                .await
+                .unwrap();
+
+            // 権限を戻す日時を過ぎた違反だけを取得する
+            let now = Utc::now().timestamp();
+            history
+                .set_access_restore(&MessageId(10), &CHANNEL_ID, &USER_ID, Some(now - 60))
+                .await
+                .unwrap();
+            let due = history.get_due_access_restores(now).await.unwrap();
+            assert_eq!(due.len(), 1);
+            assert_eq!(due[0].message_id, MessageId(10));
+
+            // 同じチャンネルで剥奪し直すと、以前の予定は取り消される
+            history
+                .set_access_restore(&MessageId(11), &CHANNEL_ID, &USER_ID, Some(now + 60))
+                .await
+                .unwrap();
+            assert!(history
+                .get_due_access_restores(now)
+                .await
+                .unwrap()
+                .is_empty());
+
+            // 取り消した違反に権限を戻す予定があれば返す
+            let restore = history.delete_violation(&MessageId(11)).await.unwrap();
+            assert_eq!(
+                restore.and_then(|record| record.access_restore_at),
+                Some(now + 60)
+            );
+            assert_eq!(
+                history.delete_violation(&MessageId(10)).await.unwrap(),
+                None
+            );
+        });
+    }
+
+    #[test]
+    fn test_approval_queue_round_trip() {
+        block_on(async {
+            let history = open_history();
+            let record = ApprovalRecord {
+                review_message_id: MessageId(30),
+                guild_id: Some(GUILD_ID),
+                channel_id: CHANNEL_ID,
+                user_id: USER_ID,
+                content: "楽しいサーバーです\ndiscord.gg/a discord.gg/b".to_string(),
+                invites: vec![
+                    ("a".to_string(), GuildId(100)),
+                    ("b".to_string(), GuildId(101)),
+                ],
+                fingerprint: "楽しいサーバーです".to_string(),
+                timestamp: minutes_ago(1),
+            };
+            history.insert_approval(&record).await.unwrap();
+
+            // 複数の招待リンクも含めて元のレコードを取得できる
+            let found = history.get_approval(&MessageId(30)).await.unwrap();
+            assert_eq!(found, Some(record));
+
+            // 承認または却下で削除するのは1回だけ
+            assert!(history.delete_approval(&MessageId(30)).await.unwrap());
+            assert!(!history.delete_approval(&MessageId(30)).await.unwrap());
+            assert_eq!(history.get_approval(&MessageId(30)).await.unwrap(), None);
+        });
+    }
+
+    /// 投稿者が鯖を再び宣伝できるようになった時のリマインダーのレコード
+    fn reminder_record(invite_guild_id: Option<u64>, due: i64) -> ReminderRecord {
+        ReminderRecord {
+            user_id: USER_ID,
+            guild_id: Some(GUILD_ID),
+            channel_id: CHANNEL_ID,
+            invite_guild_id: invite_guild_id.map(GuildId),
+            invite_code: invite_guild_id.map(|_| "a".to_string()),
+            due,
+        }
+    }
+
+    #[test]
+    fn test_reminders_replace_and_delete_sent_one() {
+        block_on(async {
+            let history = open_history();
+            let now = Utc::now().timestamp();
+            // 同じ鯖のリマインダーは登録し直すと置き換わる
+            history
+                .insert_reminder(&reminder_record(Some(100), now - 120))
+                .await
+                .unwrap();
+            let sent = reminder_record(Some(100), now - 60);
+            history.insert_reminder(&sent).await.unwrap();
+            // 宣伝回数の上限のリマインダーは別に登録できる
+            history
+                .insert_reminder(&reminder_record(None, now + 60))
+                .await
+                .unwrap();
+
+            // 通知する日時を過ぎたものだけを取得する
+            let due = history.get_due_reminders(now).await.unwrap();
+            assert_eq!(due.len(), 1);
+            assert_eq!(due[0], sent);
+
+            // 送信中に登録し直されたリマインダーは削除しない
+            let renewed = reminder_record(Some(100), now + 120);
+            history.insert_reminder(&renewed).await.unwrap();
+            history.delete_reminder(&sent).await.unwrap();
+            let due = history.get_due_reminders(now + 120).await.unwrap();
+            assert_eq!(due.len(), 2);
+            assert!(due.contains(&renewed));
+
+            // 送信したリマインダーだけを削除する
+            history.delete_reminder(&renewed).await.unwrap();
+            let due = history.get_due_reminders(now + 120).await.unwrap();
+            assert_eq!(due, [reminder_record(None, now + 60)]);
+        });
+    }
 }
@@ -1,14 +1,41 @@
 use anyhow::{anyhow, Context as _, Result};
 use chrono::prelude::*;
-use futures::future::try_join_all;
+use futures::future::{join_all, try_join_all};
+use log::warn;
 use regex::Regex;
-use serenity::model::id::GuildId;
+use reqwest::{header::LOCATION, redirect::Policy, Url};
+use serenity::http::Http;
+use serenity::model::{
+    channel::Message, id::GuildId, interactions::message_component::ActionRowComponent,
+};
+use std::collections::HashSet;
+use std::time::Duration;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::app_config::ShortenerConfig;
 
 /// パース用ギルド情報
 #[derive(Debug, Default, serde::Deserialize, PartialEq, Clone)]
 pub struct DiscordInviteGuild {
     /// ギルドID
     pub id: GuildId,
+    /// NSFWレベル (0: 未設定, 1: 成人向け, 2: 安全, 3: 年齢制限)
+    #[serde(default)]
+    pub nsfw_level: u8,
+    /// ギルドの機能
+    #[serde(default)]
+    pub features: Vec<String>,
+    /// バニティURLの招待コード
+    pub vanity_url_code: Option<String>,
+    /// ギルドの説明文
+    pub description: Option<String>,
+}
+
+/// パース用招待先チャンネル情報
+#[derive(Debug, Default, serde::Deserialize, PartialEq, Clone)]
+pub struct DiscordInviteChannel {
+    /// チャンネル名
+    pub name: Option<String>,
 }
 
 /// パース用招待コード
@@ -18,64 +45,646 @@ pub struct DiscordInvite {
     pub expires_at: Option<String>,
     /// ギルド情報
     pub guild: Option<DiscordInviteGuild>,
+    /// 招待先チャンネル情報
+    pub channel: Option<DiscordInviteChannel>,
+    /// おおよそのメンバー数
+    pub approximate_member_count: Option<u64>,
+    /// おおよそのオンラインメンバー数
+    pub approximate_presence_count: Option<u64>,
+}
+
+/// 招待リンクが見つかった場所
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum InviteSource {
+    /// メッセージ本文
+    #[default]
+    Content,
+    /// 埋め込み
+    Embed,
+    /// 添付ファイル (ファイル名、テキストファイルの中身)
+    Attachment,
+    /// ボタンなどのコンポーネント
+    Component,
+    /// スタンプ
+    Sticker,
+    /// 転送されたメッセージ
+    Forwarded,
+}
+
+impl InviteSource {
+    /// 表示名のメッセージキー
+    pub fn message_key(&self) -> &'static str {
+        match self {
+            InviteSource::Content => "source.content",
+            InviteSource::Embed => "source.embed",
+            InviteSource::Attachment => "source.attachment",
+            InviteSource::Component => "source.component",
+            InviteSource::Sticker => "source.sticker",
+            InviteSource::Forwarded => "source.forwarded",
+        }
+    }
+}
+
+/// 招待リンクの種類
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum InviteKind {
+    /// 通常の招待リンク
+    #[default]
+    Invite,
+    /// バニティURL (discord.gg/名前)
+    Vanity,
+    /// サーバー掲示板のリンク (discord.com/servers/ID)
+    Discovery,
 }
 
 /// 招待リンクの情報
 #[derive(Debug, Default, serde::Deserialize, PartialEq, Clone)]
-pub struct DiscordInviteLink<'t> {
+pub struct DiscordInviteLink {
     /// 招待リンクのURL
-    pub invite_link: &'t str,
+    pub invite_link: String,
     /// 招待コード
-    pub invite_code: &'t str,
+    pub invite_code: String,
+    /// イベントID (イベント招待の場合)
+    pub event_id: Option<String>,
+    /// 招待リンクの種類
+    #[serde(skip)]
+    pub kind: InviteKind,
+    /// 招待リンクが見つかった場所
+    #[serde(skip)]
+    pub source: InviteSource,
     /// 招待コードの有効期限
     pub expires_at: Option<DateTime<FixedOffset>>,
     /// 招待コードのギルドID
     pub guild_id: Option<GuildId>,
+    /// ギルドのおおよそのメンバー数
+    pub member_count: Option<u64>,
+    /// ギルドのおおよそのオンラインメンバー数
+    pub presence_count: Option<u64>,
+    /// ギルドのNSFWレベル
+    pub nsfw_level: u8,
+    /// ギルドの機能
+    pub guild_features: Vec<String>,
+    /// ギルドの説明文
+    pub guild_description: Option<String>,
+    /// 招待先のチャンネル名
+    pub channel_name: Option<String>,
+}
+
+impl DiscordInviteLink {
+    /// NSFW、または年齢制限のあるギルドかどうか
+    pub fn is_nsfw(&self) -> bool {
+        matches!(self.nsfw_level, 1 | 3)
+    }
+
+    /// NSFWかどうかを確認できないリンクかどうか (サーバーのプレビューにはNSFWレベルが含まれない)
+    pub fn is_nsfw_unknown(&self) -> bool {
+        self.kind == InviteKind::Discovery
+    }
+
+    /// 招待リンクの正規のURL (イベント招待の場合はイベントIDを含む)
+    pub fn url(&self) -> String {
+        match (self.kind, &self.event_id) {
+            (InviteKind::Discovery, _) => format!("https://discord.com/{}", self.invite_code),
+            (_, Some(event_id)) => {
+                format!("https://discord.gg/{}?event={}", self.invite_code, event_id)
+            }
+            (_, None) => format!("https://discord.gg/{}", self.invite_code),
+        }
+    }
+}
+
+/// Discord以外のリンク
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct ExternalLink {
+    /// URL
+    pub url: String,
+    /// ドメイン (小文字)
+    pub domain: String,
+    /// リンクが見つかった場所
+    pub source: InviteSource,
+}
+
+/// Discordのドメイン (サブドメインを含む)
+const DISCORD_DOMAINS: &[&str] = &[
+    "discord.gg",
+    "discord.com",
+    "discordapp.com",
+    "discordapp.net",
+    "discord.media",
+    "discord.new",
+    "discord.gift",
+];
+
+/// 検索対象のテキスト
+struct SourceText {
+    /// テキストが見つかった場所
+    source: InviteSource,
+    /// 投稿者が書いたテキストか (本文、フォーラムの投稿タイトル、転送されたメッセージの本文)
+    authored: bool,
+    /// テキスト (難読化解除済み)
+    text: String,
 }
 
 /// 招待リンク検索用クラス
-pub struct InviteFinder<'t> {
+pub struct InviteFinder {
     /// 招待
-    pub invite_codes: Vec<DiscordInviteLink<'t>>,
+    pub invite_codes: Vec<DiscordInviteLink>,
+    /// 検索対象のテキスト
+    texts: Vec<SourceText>,
 }
 
-impl<'t> InviteFinder<'t> {
+/// 中身を検索するテキスト添付ファイルの最大サイズ
+const MAX_TEXT_ATTACHMENT_SIZE: u64 = 64 * 1024;
+/// 1つのメッセージで辿る短縮URLの最大数
+const MAX_SHORT_LINKS: usize = 5;
+/// 短縮URLへの1回のリクエストのタイムアウト (接続から応答の受信まで)
+const SHORT_LINK_TIMEOUT: Duration = Duration::from_secs(5);
+
+impl InviteFinder {
     /// メッセージをパースする
-    pub fn new(message: &'t str) -> Result<InviteFinder<'t>> {
+    pub fn new(message: &str) -> Result<InviteFinder> {
+        Self::from_texts(vec![(InviteSource::Content, true, message.to_string())])
+    }
+
+    /// メッセージの本文、埋め込み、添付ファイルなどすべての要素から招待リンクを探す
+    pub async fn from_message(
+        msg: &Message,
+        forwarded: Option<&Message>,
+        title: Option<&str>,
+    ) -> Result<InviteFinder> {
+        // 投稿者が書いたのは本文だけで、埋め込みなどは自動で付いたもの
+        let mut texts = Self::collect_texts(msg)
+            .await
+            .into_iter()
+            .map(|(source, text)| (source, source == InviteSource::Content, text))
+            .collect::<Vec<_>>();
+        // フォーラムの投稿タイトルは本文として扱う
+        if let Some(title) = title {
+            texts.push((InviteSource::Content, true, title.to_string()));
+        }
+        // 転送されたメッセージの要素はすべて転送元として扱う
+        if let Some(forwarded) = forwarded {
+            texts.extend(
+                Self::collect_texts(forwarded)
+                    .await
+                    .into_iter()
+                    .map(|(source, text)| {
+                        (
+                            InviteSource::Forwarded,
+                            source == InviteSource::Content,
+                            text,
+                        )
+                    }),
+            );
+        }
+        Self::from_texts(texts)
+    }
+
+    /// メッセージの要素からテキストを集める
+    async fn collect_texts(msg: &Message) -> Vec<(InviteSource, String)> {
+        let mut texts = vec![(InviteSource::Content, msg.content.clone())];
+
+        // 埋め込み
+        for embed in &msg.embeds {
+            texts.extend(
+                [
+                    embed.url.clone(),
+                    embed.title.clone(),
+                    embed.description.clone(),
+                    embed.author.as_ref().map(|author| author.name.clone()),
+                    embed.author.as_ref().and_then(|author| author.url.clone()),
+                    embed.footer.as_ref().map(|footer| footer.text.clone()),
+                ]
+                .into_iter()
+                .flatten()
+                .chain(
+                    embed
+                        .fields
+                        .iter()
+                        .flat_map(|field| [field.name.clone(), field.value.clone()]),
+                )
+                .map(|text| (InviteSource::Embed, text)),
+            );
+        }
+
+        // コンポーネント
+        for row in &msg.components {
+            for component in &row.components {
+                if let ActionRowComponent::Button(button) = component {
+                    texts.extend(
+                        [button.label.clone(), button.url.clone()]
+                            .into_iter()
+                            .flatten()
+                            .map(|text| (InviteSource::Component, text)),
+                    );
+                }
+            }
+        }
+
+        // スタンプ
+        texts.extend(
+            msg.sticker_items
+                .iter()
+                .map(|sticker| (InviteSource::Sticker, sticker.name.clone())),
+        );
+
+        // 添付ファイル (テキストファイルは中身も検索する)
+        for attachment in &msg.attachments {
+            texts.push((InviteSource::Attachment, attachment.filename.clone()));
+            let is_text = attachment
+                .content_type
+                .as_ref()
+                .is_some_and(|content_type| content_type.starts_with("text/"));
+            if is_text && attachment.size <= MAX_TEXT_ATTACHMENT_SIZE {
+                match attachment.download().await {
+                    Ok(content) => texts.push((
+                        InviteSource::Attachment,
+                        String::from_utf8_lossy(&content).into_owned(),
+                    )),
+                    Err(why) => warn!(
+                        "添付ファイルのダウンロードに失敗: {}: {:?}",
+                        attachment.url, why
+                    ),
+                }
+            }
+        }
+
+        texts
+    }
+
+    /// テキストのリスト (見つかった場所, 投稿者が書いたテキストか, テキスト) から招待リンクを探す
+    fn from_texts(texts: Vec<(InviteSource, bool, String)>) -> Result<InviteFinder> {
+        // 難読化を解除
+        let texts = texts
+            .into_iter()
+            .map(|(source, authored, text)| {
+                Ok(SourceText {
+                    source,
+                    authored,
+                    text: Self::normalize(&text)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // 招待コードリストを取得
+        let mut finder = InviteFinder {
+            invite_codes: vec![],
+            texts,
+        };
+        let invite_codes = finder
+            .texts
+            .iter()
+            .map(|text| {
+                Ok(Self::find_invites(&text.text)?
+                    .into_iter()
+                    .map(|invite| DiscordInviteLink {
+                        source: text.source,
+                        ..invite
+                    })
+                    .collect::<Vec<_>>())
+            })
+            .collect::<Result<Vec<_>>>()?;
+        finder.add_invites(invite_codes.into_iter().flatten());
+
+        Ok(finder)
+    }
+
+    /// 招待コードを追加する (同じ招待コードは最初に見つかったものだけを残す)
+    fn add_invites(&mut self, invites: impl IntoIterator<Item = DiscordInviteLink>) {
+        for invite in invites {
+            if !self
+                .invite_codes
+                .iter()
+                .any(|x| x.invite_code == invite.invite_code)
+            {
+                self.invite_codes.push(invite);
+            }
+        }
+    }
+
+    /// 招待リンクを隠すための難読化を解除する
+    fn normalize(message: &str) -> Result<String> {
+        // 全角文字などを半角に変換し、ゼロ幅文字などの見えない文字を取り除く
+        let message = message
+            .nfkc()
+            .filter(|c| {
+                !matches!(
+                    c,
+                    '\u{00AD}'
+                        | '\u{034F}'
+                        | '\u{180E}'
+                        | '\u{200B}'..='\u{200F}'
+                        | '\u{2060}'..='\u{2064}'
+                        | '\u{FEFF}'
+                )
+            })
+            .collect::<String>();
+
+        // Markdownの装飾やエスケープを取り除く (招待コードに使われる `_` は残す)
+        let message = message.replace(['*', '~', '`', '\\'], "");
+
+        // discord(.)gg, discord[.]gg, discord dot gg などのドットを置き換える
+        let dot_regex =
+            Regex::new(r"(?i)\b(discord(?:app)?)\s*(?:\(\.\)|\[\.\]|\{\.\}|\(dot\)|\[dot\]|\s+dot\s+|\.)\s*(gg|com|io|me|li)\b")
+                .context("正規表現のパターンの作成に失敗")?;
+        let message = dot_regex.replace_all(&message, "$1.$2");
+
+        // discord.gg / abc のようなスラッシュ前後の空白を取り除く
+        let slash_regex =
+            Regex::new(r"(?i)\b(discord(?:app)?\.(?:gg|com|io|me|li))\s*/\s*(?:(invite)\s*/\s*)?")
+                .context("正規表現のパターンの作成に失敗")?;
+        let message = slash_regex.replace_all(&message, |c: &regex::Captures| match c.get(2) {
+            Some(invite) => format!("{}/{}/", &c[1], invite.as_str()),
+            None => format!("{}/", &c[1]),
+        });
+
+        Ok(message.into_owned())
+    }
+
+    /// 検索対象のテキスト (難読化解除済み) を取得する
+    pub fn texts(&self) -> impl Iterator<Item = &str> {
+        self.texts.iter().map(|text| text.text.as_str())
+    }
+
+    /// Discord以外のリンクを取得する (招待リンクに展開された短縮URLは除く)
+    /// リンクのプレビューなど自動で付いた埋め込みで投稿が後から削除されないよう、投稿者が書いたテキストだけを探す
+    pub fn external_links(&self) -> Result<Vec<ExternalLink>> {
+        // URLを探す正規表現パターンを準備
+        let url_regex = Regex::new(r#"(?i)\b(?:https?://|www\.)[^\s<>()\[\]{}"'|`]+"#)
+            .context("URLの正規表現のパターンの作成に失敗")?;
+
+        let mut links: Vec<ExternalLink> = vec![];
+        for text in self.texts.iter().filter(|text| text.authored) {
+            for found in url_regex.find_iter(&text.text) {
+                let url = found
+                    .as_str()
+                    .trim_end_matches(['.', ',', '!', '?', ':', ';']);
+                // 招待リンクとして扱われている短縮URLは除く
+                if self
+                    .invite_codes
+                    .iter()
+                    .any(|invite| invite.invite_link == url)
+                {
+                    continue;
+                }
+                // ドメインを取得
+                let parsed = if url.contains("://") {
+                    Url::parse(url)
+                } else {
+                    Url::parse(&format!("https://{}", url))
+                };
+                let domain = match parsed
+                    .ok()
+                    .and_then(|url| url.host_str().map(str::to_lowercase))
+                {
+                    Some(domain) => domain,
+                    None => continue,
+                };
+                // Discordのリンクは除く
+                if DISCORD_DOMAINS.iter().any(|d| domain_matches(&domain, d)) {
+                    continue;
+                }
+                // 本文と転送元などで同じURLが複数回出てくるため重複を除く
+                if links.iter().any(|link| link.url == url) {
+                    continue;
+                }
+                links.push(ExternalLink {
+                    url: url.to_string(),
+                    domain,
+                    source: text.source,
+                });
+            }
+        }
+
+        Ok(links)
+    }
+
+    /// 本文から招待リンクを取り除いた説明文を取得する
+    pub fn description(&self) -> String {
+        let content = self
+            .texts
+            .iter()
+            .filter(|text| text.source == InviteSource::Content)
+            .map(|text| text.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.invite_codes
+            .iter()
+            .filter(|invite_link| invite_link.source == InviteSource::Content)
+            .fold(content, |content, invite_link| {
+                content.replace(&invite_link.invite_link, "")
+            })
+    }
+
+    /// テキストから招待リンクを探す
+    fn find_invites(message: &str) -> Result<Vec<DiscordInviteLink>> {
         // 正規表現パターンを準備
+        // - discord.gg/CODE, discord.gg/invite/CODE
+        // - (www.|ptb.|canary.)discord.com/invite/CODE, (www.|ptb.|canary.)discordapp.com/invite/CODE
+        // - 上記の末尾に ?event=ID (イベント招待)
         let invite_regex = Regex::new(
-            r"(?:https?://)?(?:discord\.(?:gg|io|me|li)|(?:discord|discordapp)\.com/invite)/([A-Za-z1-9]+)",
+            r"(?i)\b(?:https?://)?(?:(?:www\.|ptb\.|canary\.)?discord(?:app)?\.com/invite|discord\.gg(?:/invite)?)/([A-Za-z0-9_-]+)/?(?:\?event=(\d+))?",
+        )
+        .context("正規表現のパターンの作成に失敗")?;
+        // サーバー掲示板のリンク
+        // - (www.|ptb.|canary.)discord.com/servers/NAME-ID, (www.|ptb.|canary.)discord.com/servers/ID
+        let discovery_regex = Regex::new(
+            r"(?i)\b(?:https?://)?(?:www\.|ptb\.|canary\.)?discord(?:app)?\.com/servers/(?:[A-Za-z0-9_-]*-)?(\d{17,20})\b/?",
         )
         .context("正規表現のパターンの作成に失敗")?;
 
         // 招待コードリストを取得
-        let invite_codes = invite_regex
-            .captures_iter(message)
-            .map(|c| {
-                Ok(DiscordInviteLink {
-                    invite_link: c
-                        .get(0)
-                        .ok_or_else(|| anyhow!("招待リンクのパース失敗"))?
-                        .as_str(),
+        let invites = invite_regex.captures_iter(message).map(|c| {
+            let link = c.get(0).ok_or_else(|| anyhow!("招待リンクのパース失敗"))?;
+            Ok((
+                link.start(),
+                DiscordInviteLink {
+                    invite_link: link.as_str().to_string(),
                     invite_code: c
                         .get(1)
                         .ok_or_else(|| anyhow!("招待コードのパース失敗"))?
-                        .as_str(),
-                    expires_at: None,
-                    guild_id: None,
-                })
+                        .as_str()
+                        .to_string(),
+                    event_id: c.get(2).map(|m| m.as_str().to_string()),
+                    ..Default::default()
+                },
+            ))
+        });
+        // サーバー掲示板のリンクはURLからギルドIDを取得する (IDとして扱えない数値のリンクは無視する)
+        let discoveries = discovery_regex.captures_iter(message).filter_map(|c| {
+            let link = c.get(0)?;
+            let guild_id = c.get(1)?.as_str();
+            let id = guild_id.parse().ok()?;
+            Some(Ok((
+                link.start(),
+                DiscordInviteLink {
+                    invite_link: link.as_str().to_string(),
+                    invite_code: format!("servers/{}", guild_id),
+                    kind: InviteKind::Discovery,
+                    guild_id: Some(GuildId(id)),
+                    ..Default::default()
+                },
+            )))
+        });
+
+        // メッセージ中の出現順に並べる
+        let mut invites = invites
+            .chain(discoveries)
+            .collect::<Result<Vec<(usize, DiscordInviteLink)>>>()?;
+        invites.sort_by_key(|(start, _invite)| *start);
+        Ok(invites.into_iter().map(|(_start, invite)| invite).collect())
+    }
+
+    /// 短縮URLのリダイレクト先から招待リンクを探す
+    pub async fn resolve_shortened_links(&mut self, shortener: &ShortenerConfig) -> Result<()> {
+        if !shortener.follow_redirects || shortener.domains.is_empty() {
+            return Ok(());
+        }
+
+        // 短縮URLを探す正規表現パターンを準備
+        let domains = shortener
+            .domains
+            .iter()
+            .map(|domain| regex::escape(domain))
+            .collect::<Vec<_>>()
+            .join("|");
+        let shortener_regex = Regex::new(&format!(
+            r#"(?i)\b(?:https?://)?(?:www\.)?(?:{})/[^\s<>()\[\]{{}}"'|`]+"#,
+            domains
+        ))
+        .context("短縮URLの正規表現のパターンの作成に失敗")?;
+
+        // 短縮URLリストを取得 (同じURLは1回だけ辿る)
+        let mut short_links = self
+            .texts
+            .iter()
+            .flat_map(|text| {
+                shortener_regex
+                    .find_iter(&text.text)
+                    .map(move |m| (text.source, m.as_str()))
             })
-            .collect::<Result<Vec<DiscordInviteLink>>>()?;
+            .collect::<Vec<_>>();
+        let mut seen = HashSet::new();
+        short_links.retain(|(_source, short_link)| seen.insert(short_link.to_lowercase()));
+        if short_links.is_empty() {
+            return Ok(());
+        }
+        // 大量のリクエストを送らないよう、辿る数を制限する
+        if short_links.len() > MAX_SHORT_LINKS {
+            warn!(
+                "短縮URLが多すぎるため、先頭の{}件のみ辿ります: {}件",
+                MAX_SHORT_LINKS,
+                short_links.len()
+            );
+            short_links.truncate(MAX_SHORT_LINKS);
+        }
 
-        Ok(InviteFinder { invite_codes })
+        // リダイレクトを自動で辿らないクライアントを作成 (タイムアウトした場合は解決できなかったものとして扱う)
+        let client = reqwest::Client::builder()
+            .redirect(Policy::none())
+            .timeout(SHORT_LINK_TIMEOUT)
+            .build()
+            .context("HTTPクライアントの作成に失敗")?;
+
+        // リダイレクト先を辿って招待リンクを探す
+        let invites = join_all(short_links.into_iter().map(|(source, short_link)| {
+            let client = &client;
+            async move {
+                match Self::follow_redirects(client, short_link, shortener).await {
+                    Ok(invites) => invites
+                        .into_iter()
+                        .map(|invite| DiscordInviteLink {
+                            // メッセージ中のリンクは短縮URLのまま扱う
+                            invite_link: short_link.to_string(),
+                            source,
+                            ..invite
+                        })
+                        .collect::<Vec<_>>(),
+                    Err(why) => {
+                        warn!(
+                            "短縮URLのリダイレクト先の取得に失敗: {}: {:?}",
+                            short_link, why
+                        );
+                        vec![]
+                    }
+                }
+            }
+        }))
+        .await;
+        self.add_invites(invites.into_iter().flatten());
+
+        Ok(())
+    }
+
+    /// リダイレクトを辿り、途中で見つかった招待リンクを返す (短縮URLのドメイン以外へはリクエストしない)
+    async fn follow_redirects(
+        client: &reqwest::Client,
+        short_link: &str,
+        shortener: &ShortenerConfig,
+    ) -> Result<Vec<DiscordInviteLink>> {
+        // スキームがない場合は補う
+        let mut url = if short_link.contains("://") {
+            Url::parse(short_link)
+        } else {
+            Url::parse(&format!("https://{}", short_link))
+        }
+        .with_context(|| format!("短縮URLのパースに失敗: {}", short_link))?;
+
+        for _ in 0..shortener.max_redirects {
+            // 短縮URLのドメインでなければ辿らない
+            let is_shortener = matches!(url.scheme(), "http" | "https")
+                && url.host_str().is_some_and(|host| {
+                    let host = host.to_lowercase();
+                    let host = host.trim_start_matches("www.");
+                    shortener
+                        .domains
+                        .iter()
+                        .any(|domain| domain_matches(host, domain))
+                });
+            if !is_shortener {
+                break;
+            }
+
+            // リダイレクト先を取得
+            let response = client
+                .get(url.clone())
+                .send()
+                .await
+                .with_context(|| format!("短縮URLへのリクエストに失敗: {}", url))?;
+            if !response.status().is_redirection() {
+                break;
+            }
+            let location = match response.headers().get(LOCATION) {
+                Some(location) => location
+                    .to_str()
+                    .context("リダイレクト先のURLのパースに失敗")?,
+                None => break,
+            };
+            url = url
+                .join(location)
+                .with_context(|| format!("リダイレクト先のURLのパースに失敗: {}", location))?;
+
+            // リダイレクト先が招待リンクであれば終了
+            let invites = Self::find_invites(url.as_str())?;
+            if !invites.is_empty() {
+                return Ok(invites);
+            }
+        }
+
+        Ok(vec![])
     }
 
     /// APIから招待リンクの詳細を取得する
-    pub async fn get_invite_list(&self) -> Result<Vec<DiscordInviteLink<'t>>> {
+    pub async fn get_invite_list(&self, http: &Http) -> Result<Vec<DiscordInviteLink>> {
         try_join_all(self.invite_codes.iter().map(|invite_link| async move {
-            // APIリクエストを構築
+            // サーバー掲示板のリンクはサーバーのプレビューから取得する
+            if invite_link.kind == InviteKind::Discovery {
+                return Self::get_discovery_info(http, invite_link).await;
+            }
+
+            // APIリクエストを構築 (メンバー数も取得する)
             let invite_url = format!(
-                "https://discord.com/api/v10/invites/{}",
+                "https://discord.com/api/v10/invites/{}?with_counts=true",
                 invite_link.invite_code
             );
             // APIリクエストを実行
@@ -96,16 +705,403 @@ impl<'t> InviteFinder<'t> {
                 ),
                 None => None, // 無期限リンク
             };
-            // 招待リンクのギルドIDを抽出
-            let guild_id = invite_result.guild.map(|g| g.id);
+            // 招待リンクのギルド情報を抽出
+            let guild_id = invite_result.guild.as_ref().map(|g| g.id);
+            let guild = invite_result.guild.unwrap_or_default();
+            // ギルドのバニティURLと一致すればバニティ招待
+            let kind = match &guild.vanity_url_code {
+                Some(vanity) if vanity.eq_ignore_ascii_case(&invite_link.invite_code) => {
+                    InviteKind::Vanity
+                }
+                _ => InviteKind::Invite,
+            };
 
             // 有効期限をセットした構造体を返す
             Ok(DiscordInviteLink {
+                kind,
                 expires_at,
                 guild_id,
-                ..*invite_link
+                member_count: invite_result.approximate_member_count,
+                presence_count: invite_result.approximate_presence_count,
+                nsfw_level: guild.nsfw_level,
+                guild_features: guild.features,
+                guild_description: guild.description,
+                channel_name: invite_result.channel.and_then(|channel| channel.name),
+                ..invite_link.clone()
             })
         }))
         .await
     }
+
+    /// サーバー掲示板のリンクの詳細をサーバーのプレビューから取得する
+    async fn get_discovery_info(
+        http: &Http,
+        invite_link: &DiscordInviteLink,
+    ) -> Result<DiscordInviteLink> {
+        let guild_id = invite_link
+            .guild_id
+            .ok_or_else(|| anyhow!("サーバーリンクにギルドIDがありません"))?;
+
+        // 公開されていないサーバーは無効なリンクとして扱う
+        let preview = match http.get_guild_preview(guild_id.0).await {
+            Ok(preview) => preview,
+            Err(why) => {
+                warn!("サーバーのプレビューの取得に失敗: {}: {:?}", guild_id, why);
+                return Ok(DiscordInviteLink {
+                    guild_id: None,
+                    ..invite_link.clone()
+                });
+            }
+        };
+
+        Ok(DiscordInviteLink {
+            member_count: Some(preview.approximate_member_count),
+            presence_count: Some(preview.approximate_presence_count),
+            guild_features: preview.features,
+            guild_description: preview.description,
+            ..invite_link.clone()
+        })
+    }
+}
+
+/// ドメインが指定されたドメイン、またはそのサブドメインかどうか
+pub fn domain_matches(domain: &str, pattern: &str) -> bool {
+    let pattern = pattern.trim_start_matches("*.").to_lowercase();
+    domain == pattern || domain.ends_with(&format!(".{}", pattern))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (招待リンク, 招待コード, イベントID)
+    type Expected<'a> = (&'a str, &'a str, Option<&'a str>);
+
+    #[test]
+    fn test_find_invite_codes() {
+        // (メッセージ, 見つかるべき招待リンク)
+        let cases: &[(&str, &[Expected])] = &[
+            // 基本形
+            ("discord.gg/abc", &[("discord.gg/abc", "abc", None)]),
+            (
+                "https://discord.gg/abc",
+                &[("https://discord.gg/abc", "abc", None)],
+            ),
+            (
+                "http://discord.gg/abc",
+                &[("http://discord.gg/abc", "abc", None)],
+            ),
+            (
+                "https://discord.gg/invite/abc",
+                &[("https://discord.gg/invite/abc", "abc", None)],
+            ),
+            // 0, -, _ を含む招待コード
+            (
+                "https://discord.gg/a0b-c_d",
+                &[("https://discord.gg/a0b-c_d", "a0b-c_d", None)],
+            ),
+            (
+                "https://discord.gg/1234567890",
+                &[("https://discord.gg/1234567890", "1234567890", None)],
+            ),
+            // discord.com / discordapp.com
+            (
+                "https://discord.com/invite/abc",
+                &[("https://discord.com/invite/abc", "abc", None)],
+            ),
+            (
+                "https://www.discord.com/invite/abc",
+                &[("https://www.discord.com/invite/abc", "abc", None)],
+            ),
+            (
+                "https://ptb.discord.com/invite/abc",
+                &[("https://ptb.discord.com/invite/abc", "abc", None)],
+            ),
+            (
+                "https://canary.discord.com/invite/abc",
+                &[("https://canary.discord.com/invite/abc", "abc", None)],
+            ),
+            (
+                "https://discordapp.com/invite/abc",
+                &[("https://discordapp.com/invite/abc", "abc", None)],
+            ),
+            (
+                "https://www.discordapp.com/invite/abc",
+                &[("https://www.discordapp.com/invite/abc", "abc", None)],
+            ),
+            (
+                "https://canary.discordapp.com/invite/abc",
+                &[("https://canary.discordapp.com/invite/abc", "abc", None)],
+            ),
+            // 大文字のドメイン
+            (
+                "HTTPS://Discord.GG/AbC",
+                &[("HTTPS://Discord.GG/AbC", "AbC", None)],
+            ),
+            // 外部サービスは招待リンクとして扱わない (短縮URLとして解決する)
+            ("discord.io/abc", &[]),
+            ("discord.me/abc", &[]),
+            ("discord.li/abc", &[]),
+            // イベント招待
+            (
+                "https://discord.gg/abc?event=1234567890",
+                &[(
+                    "https://discord.gg/abc?event=1234567890",
+                    "abc",
+                    Some("1234567890"),
+                )],
+            ),
+            (
+                "https://discord.com/invite/abc?event=1234567890",
+                &[(
+                    "https://discord.com/invite/abc?event=1234567890",
+                    "abc",
+                    Some("1234567890"),
+                )],
+            ),
+            (
+                "https://discord.gg/abc/?event=1234567890",
+                &[(
+                    "https://discord.gg/abc/?event=1234567890",
+                    "abc",
+                    Some("1234567890"),
+                )],
+            ),
+            // 文中のリンク
+            (
+                "サーバーです！ https://discord.gg/abc よろしく",
+                &[("https://discord.gg/abc", "abc", None)],
+            ),
+            (
+                "<https://discord.gg/abc>",
+                &[("https://discord.gg/abc", "abc", None)],
+            ),
+            (
+                "[参加する](https://discord.gg/abc)",
+                &[("https://discord.gg/abc", "abc", None)],
+            ),
+            (
+                "https://discord.gg/abc。",
+                &[("https://discord.gg/abc", "abc", None)],
+            ),
+            // 複数のリンク
+            (
+                "discord.gg/abc\nhttps://discord.com/invite/def",
+                &[
+                    ("discord.gg/abc", "abc", None),
+                    ("https://discord.com/invite/def", "def", None),
+                ],
+            ),
+            // サーバー掲示板のリンク
+            (
+                "https://discord.com/servers/123456789012345678",
+                &[(
+                    "https://discord.com/servers/123456789012345678",
+                    "servers/123456789012345678",
+                    None,
+                )],
+            ),
+            (
+                "https://discord.com/servers/my-community-123456789012345678",
+                &[(
+                    "https://discord.com/servers/my-community-123456789012345678",
+                    "servers/123456789012345678",
+                    None,
+                )],
+            ),
+            (
+                "https://discord.gg/abc https://www.discord.com/servers/123456789012345678",
+                &[
+                    ("https://discord.gg/abc", "abc", None),
+                    (
+                        "https://www.discord.com/servers/123456789012345678",
+                        "servers/123456789012345678",
+                        None,
+                    ),
+                ],
+            ),
+            // 招待リンクではないもの
+            ("https://discord.com/servers/", &[]),
+            ("https://discord.com/channels/1/2/3", &[]),
+            ("https://discord.com/invite/", &[]),
+            ("https://notdiscord.gg/abc", &[]),
+            ("https://example.com/", &[]),
+        ];
+
+        for (message, expected) in cases {
+            let finder = InviteFinder::new(message).unwrap();
+            let actual = finder
+                .invite_codes
+                .iter()
+                .map(|x| {
+                    (
+                        x.invite_link.as_str(),
+                        x.invite_code.as_str(),
+                        x.event_id.as_deref(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(&actual, expected, "message: {}", message);
+        }
+    }
+
+    #[test]
+    fn test_find_obfuscated_invite_codes() {
+        // (メッセージ, 見つかるべき招待リンク)
+        let cases: &[(&str, &[Expected])] = &[
+            // 空白を挟んだリンク
+            ("discord . gg/abc", &[("discord.gg/abc", "abc", None)]),
+            ("discord.gg / abc", &[("discord.gg/abc", "abc", None)]),
+            (
+                "https://discord . com / invite / abc",
+                &[("https://discord.com/invite/abc", "abc", None)],
+            ),
+            // ドットの言い換え
+            ("discord(.)gg/abc", &[("discord.gg/abc", "abc", None)]),
+            ("discord[.]gg/abc", &[("discord.gg/abc", "abc", None)]),
+            ("discord dot gg/abc", &[("discord.gg/abc", "abc", None)]),
+            // ゼロ幅文字
+            (
+                "discord\u{200B}.gg/\u{200D}abc",
+                &[("discord.gg/abc", "abc", None)],
+            ),
+            ("disc\u{FEFF}ord.gg/abc", &[("discord.gg/abc", "abc", None)]),
+            // 全角文字
+            (
+                "ｄｉｓｃｏｒｄ．ｇｇ／ａｂｃ",
+                &[("discord.gg/abc", "abc", None)],
+            ),
+            // Markdownの装飾
+            ("disc**ord**.gg/abc", &[("discord.gg/abc", "abc", None)]),
+            ("discord.gg/~~a~~bc", &[("discord.gg/abc", "abc", None)]),
+            ("`discord.gg/abc`", &[("discord.gg/abc", "abc", None)]),
+            ("discord\\.gg/abc", &[("discord.gg/abc", "abc", None)]),
+            // Markdownのマスクリンク
+            (
+                "[ここをクリック](https://discord.gg/abc)",
+                &[("https://discord.gg/abc", "abc", None)],
+            ),
+            // 招待リンクではないもの
+            ("discord gg abc", &[]),
+        ];
+
+        for (message, expected) in cases {
+            let finder = InviteFinder::new(message).unwrap();
+            let actual = finder
+                .invite_codes
+                .iter()
+                .map(|x| {
+                    (
+                        x.invite_link.as_str(),
+                        x.invite_code.as_str(),
+                        x.event_id.as_deref(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(&actual, expected, "message: {}", message);
+        }
+    }
+
+    #[test]
+    fn test_find_discovery_guild_id() {
+        let finder =
+            InviteFinder::new("https://discord.com/servers/my-community-123456789012345678")
+                .unwrap();
+        let invite = finder.invite_codes.first().unwrap();
+        assert_eq!(invite.kind, InviteKind::Discovery);
+        assert_eq!(invite.guild_id, Some(GuildId(123456789012345678)));
+
+        // u64に収まらないIDのリンクは無視し、他のリンクの検索は続ける
+        let finder = InviteFinder::new(
+            "https://discord.com/servers/99999999999999999999 https://discord.gg/abc",
+        )
+        .unwrap();
+        let codes = finder
+            .invite_codes
+            .iter()
+            .map(|invite| invite.invite_code.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(codes, ["abc"]);
+    }
+
+    #[test]
+    fn test_invite_url() {
+        // (メッセージ, 正規のURL)
+        let cases = [
+            ("discord . gg / abc", "https://discord.gg/abc"),
+            (
+                "discord.gg/abc?event=123456789012345678",
+                "https://discord.gg/abc?event=123456789012345678",
+            ),
+            (
+                "https://discord.com/servers/my-community-123456789012345678",
+                "https://discord.com/servers/123456789012345678",
+            ),
+        ];
+        for (message, expected) in cases {
+            let finder = InviteFinder::new(message).unwrap();
+            let invite = finder.invite_codes.first().unwrap();
+            assert_eq!(invite.url(), expected, "message: {}", message);
+        }
+    }
+
+    #[test]
+    fn test_find_external_links() {
+        // (メッセージ, 見つかるべきリンクとドメイン)
+        let cases: &[(&str, &[(&str, &str)])] = &[
+            ("discord.gg/abc", &[]),
+            ("https://discord.com/channels/1/2", &[]),
+            (
+                "https://www.youtube.com/watch?v=abc discord.gg/abc",
+                &[("https://www.youtube.com/watch?v=abc", "www.youtube.com")],
+            ),
+            (
+                "詳細は www.Example.com/page. を見てね",
+                &[("www.Example.com/page", "www.example.com")],
+            ),
+            (
+                "<https://example.com/a> https://example.com/a",
+                &[("https://example.com/a", "example.com")],
+            ),
+        ];
+        for (message, expected) in cases {
+            let finder = InviteFinder::new(message).unwrap();
+            let links = finder.external_links().unwrap();
+            let links = links
+                .iter()
+                .map(|link| (link.url.as_str(), link.domain.as_str()))
+                .collect::<Vec<_>>();
+            assert_eq!(&links, expected, "{}", message);
+        }
+
+        // リンクのプレビューなど自動で付いた埋め込みのリンクは数えない
+        let finder = InviteFinder::from_texts(vec![
+            (
+                InviteSource::Content,
+                true,
+                "https://example.com/a".to_string(),
+            ),
+            (
+                InviteSource::Embed,
+                false,
+                "https://example.com/b".to_string(),
+            ),
+        ])
+        .unwrap();
+        let links = finder.external_links().unwrap();
+        let urls = links
+            .iter()
+            .map(|link| link.url.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(urls, ["https://example.com/a"]);
+    }
+
+    #[test]
+    fn test_domain_matches() {
+        assert!(domain_matches("example.com", "example.com"));
+        assert!(domain_matches("www.example.com", "example.com"));
+        assert!(domain_matches("www.example.com", "*.example.com"));
+        assert!(!domain_matches("badexample.com", "example.com"));
+        assert!(!domain_matches("example.com.evil.net", "example.com"));
+    }
 }
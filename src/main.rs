@@ -1,12 +1,16 @@
 mod app_config;
+mod content_filter;
+mod description;
 mod event_handler;
 mod history_log;
 mod invite_finder;
+mod message_catalog;
 
-use anyhow::{Context as _, Result};
+use anyhow::{bail, Context as _, Result};
 use app_config::AppConfig;
 use event_handler::Handler;
 use history_log::HistoryLog;
+use message_catalog::MessageCatalog;
 use std::env;
 
 use serenity::prelude::*;
@@ -26,8 +30,22 @@ async fn main() -> Result<()> {
     // データベースを初期化
     let history = HistoryLog::new(&basedir, app_config.ban_period.clone())?;
 
+    // メッセージを読み込む
+    let messages = MessageCatalog::load(&basedir).context("メッセージの読み込みに失敗")?;
+    // 設定された言語のメッセージがあるか確認する
+    let message_config = &app_config.message;
+    for locale in std::iter::once(&message_config.locale)
+        .chain(message_config.guild_locales.values())
+        .chain(message_config.channel_locales.values())
+    {
+        if !messages.has_locale(locale) {
+            bail!("メッセージの言語が見つかりません: {}", locale);
+        }
+    }
+
     // イベント受信リスナーを構築
-    let handler = Handler::new(app_config, history).context("イベント受信リスナーの構築に失敗")?;
+    let handler =
+        Handler::new(app_config, history, messages).context("イベント受信リスナーの構築に失敗")?;
 
     // 環境変数のトークンを使用してDiscord APIを初期化
     let token = env::var("DISCORD_TOKEN").context("トークンが指定されていません")?;
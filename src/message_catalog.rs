@@ -0,0 +1,145 @@
+use anyhow::{Context as _, Result};
+use config::{Config, File, FileFormat};
+use log::warn;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// 同梱のメッセージ (ロケール, TOML)
+const BUNDLED_MESSAGES: &[(&str, &str)] = &[
+    ("ja", include_str!("messages/ja.toml")),
+    ("en", include_str!("messages/en.toml")),
+];
+
+/// メッセージが見つからない場合に使用するロケール
+const FALLBACK_LOCALE: &str = "ja";
+
+/// メッセージカタログ
+pub struct MessageCatalog {
+    /// ロケールごとのメッセージ ("セクション.キー" → テンプレート)
+    locales: HashMap<String, HashMap<String, String>>,
+}
+
+impl MessageCatalog {
+    /// 同梱のメッセージと、basedir/messages/*.toml のカスタムメッセージを読み込む
+    pub fn load(basedir: &str) -> Result<MessageCatalog> {
+        let mut catalog = MessageCatalog {
+            locales: HashMap::new(),
+        };
+
+        // 同梱のメッセージを読み込む
+        for (locale, source) in BUNDLED_MESSAGES {
+            catalog
+                .merge(locale, source)
+                .with_context(|| format!("同梱のメッセージの読み込みに失敗: {}", locale))?;
+        }
+
+        // カスタムメッセージで上書きする (ファイル名がロケールになる)
+        let dir = Path::new(basedir).join("messages");
+        if dir.is_dir() {
+            for entry in std::fs::read_dir(&dir)
+                .with_context(|| format!("メッセージフォルダの読み込みに失敗: {:?}", dir))?
+            {
+                let path = entry?.path();
+                if path.extension().and_then(|ext| ext.to_str()) != Some("toml") {
+                    continue;
+                }
+                let locale = match path.file_stem().and_then(|stem| stem.to_str()) {
+                    Some(locale) => locale.to_string(),
+                    None => continue,
+                };
+                let source = std::fs::read_to_string(&path)
+                    .with_context(|| format!("メッセージファイルの読み込みに失敗: {:?}", path))?;
+                catalog
+                    .merge(&locale, &source)
+                    .with_context(|| format!("メッセージファイルのパースに失敗: {:?}", path))?;
+            }
+        }
+
+        Ok(catalog)
+    }
+
+    /// TOMLのメッセージを読み込み、既存のメッセージを上書きする
+    fn merge(&mut self, locale: &str, source: &str) -> Result<()> {
+        let sections = Config::builder()
+            .add_source(File::from_str(source, FileFormat::Toml))
+            .build()?
+            .try_deserialize::<HashMap<String, HashMap<String, String>>>()?;
+
+        let messages = self.locales.entry(locale.to_string()).or_default();
+        for (section, keys) in sections {
+            for (key, template) in keys {
+                messages.insert(format!("{}.{}", section, key), template);
+            }
+        }
+        Ok(())
+    }
+
+    /// ロケールが存在するか
+    pub fn has_locale(&self, locale: &str) -> bool {
+        self.locales.contains_key(locale)
+    }
+
+    /// メッセージを取得し、{名前} を値に置き換える
+    pub fn get(&self, locale: &str, key: &str, args: &[(&str, String)]) -> String {
+        // 指定されたロケール → 既定のロケールの順に探す
+        let template = [locale, FALLBACK_LOCALE]
+            .iter()
+            .find_map(|locale| self.locales.get(*locale)?.get(key));
+        let template = match template {
+            Some(template) => template,
+            None => {
+                warn!("メッセージが見つかりません: {}.{}", locale, key);
+                return key.to_string();
+            }
+        };
+
+        // 置き換えた値に含まれる {名前} は置き換えないよう、テンプレートを1回だけ走査する
+        let mut text = String::with_capacity(template.len());
+        let mut rest = template.as_str();
+        while let Some(start) = rest.find('{') {
+            text.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            let arg = after.find('}').and_then(|end| {
+                let (_name, value) = args.iter().find(|(name, _value)| *name == &after[..end])?;
+                Some((end, value))
+            });
+            match arg {
+                Some((end, value)) => {
+                    text.push_str(value);
+                    rest = &after[end + 1..];
+                }
+                None => {
+                    text.push('{');
+                    rest = after;
+                }
+            }
+        }
+        text.push_str(rest);
+        text
+    }
+
+    /// 指定されたロケールのメッセージを取得する (既定のロケールは探さず、置き換えもしない)
+    pub fn find(&self, locale: &str, key: &str) -> Option<&str> {
+        self.locales.get(locale)?.get(key).map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_replaces_in_single_pass() {
+        let catalog = MessageCatalog::load("not-found").unwrap();
+        // 値に含まれる {date} は置き換えない
+        let text = catalog.get(
+            "ja",
+            "reminder.scheduled",
+            &[("date", "{date}".to_string())],
+        );
+        assert_eq!(text, "{date} になったらDMでお知らせします");
+        // 引数にない {名前} はそのまま残す
+        let text = catalog.get("ja", "reminder.due", &[]);
+        assert_eq!(text, "{channel} で再び宣伝できるようになりました");
+    }
+}